@@ -1,16 +1,27 @@
-use chrono::{Datelike, FixedOffset, NaiveDate, TimeZone, Utc};
+use crate::calendar::*;
+use crate::clock::Clock;
+use chrono::{Datelike, FixedOffset, TimeZone};
 use futures::future::join_all;
-use log::info;
+use log::{debug, info, warn};
+use rand::{thread_rng, Rng};
 use reqwest::header::{HeaderMap, HeaderValue, COOKIE, USER_AGENT};
 use reqwest::redirect::Policy;
-use reqwest::{Client, RequestBuilder};
-use serde_json::Value;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Map, Value};
 use std::cmp::Ordering;
-use std::collections::{hash_map::Iter, HashMap, HashSet};
+use std::collections::hash_map::{Entry, Iter};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::{self, Runtime};
+use tokio::time::delay_for;
 
 pub type EventYear = i32;
 pub type MemberId = i64;
@@ -22,18 +33,16 @@ pub type CompletionLevel = u8;
 pub type Score = usize;
 
 pub const FIRST_EVENT_YEAR: EventYear = 2015;
-const NUM_PUZZLE_DAYS: PuzzleDay = 25;
-const EVENT_START_DAY: u32 = 1;
-const EVENT_START_MONTH: u32 = 12;
-const RELEASE_TIMEZONE_OFFSET: i32 = -5 * 3600;
+const THROTTLE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 const PKG_REPO: &str = env!("CARGO_PKG_REPOSITORY");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub fn latest_event_year() -> i32 {
+pub fn latest_event_year(clock: &Clock) -> i32 {
     let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
         .unwrap()
-        .from_utc_datetime(&Utc::now().naive_utc());
+        .from_utc_datetime(&clock.now().naive_utc());
     if now.month() < EVENT_START_MONTH {
         now.year() - 1
     } else {
@@ -41,46 +50,26 @@ pub fn latest_event_year() -> i32 {
     }
 }
 
-pub fn last_unlock_day(year: i32) -> i64 {
-    let timezone: FixedOffset =
-        FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
-    let Some(puzzle_date) = NaiveDate::from_ymd_opt(
-        year, EVENT_START_MONTH, EVENT_START_DAY
-    ) else {
-        return 0;
-    };
-
-    if let Some(event_start) = timezone
-        .from_local_datetime(&puzzle_date.and_hms_opt(0, 0, 0).unwrap())
-        .single()
-    {
-        let duration = timezone
-            .from_utc_datetime(&Utc::now().naive_utc())
-            .signed_duration_since(event_start);
-        if duration.num_milliseconds() >= 0 {
-            return (1 + duration.num_days()).min(NUM_PUZZLE_DAYS.into());
-        }
-    }
-    0
-}
-
-pub fn is_valid_event_year(year: i32) -> bool {
-    year >= FIRST_EVENT_YEAR && year <= latest_event_year()
+pub fn is_valid_event_year(year: i32, clock: &Clock) -> bool {
+    year >= FIRST_EVENT_YEAR && year <= latest_event_year(clock)
 }
 
 #[derive(Eq, Debug)]
 pub struct Member {
     id: MemberId,
     name: String,
+    anonymous: bool,
     completed: HashMap<PuzzleId, Timestamp>,
 }
 
 impl Member {
     fn new(id: MemberId, opt_name: Option<String>) -> Self {
+        let anonymous = opt_name.is_none();
         let name = opt_name.unwrap_or(format!("(anonymous user #{})", id));
         Self {
             id,
             name,
+            anonymous,
             completed: HashMap::new(),
         }
     }
@@ -93,12 +82,16 @@ impl Member {
         &self.name
     }
 
-    pub fn get_stars(&self, as_of: Option<Timestamp>) -> Vec<CompletionLevel> {
-        let mut stars = vec![0; usize::from(NUM_PUZZLE_DAYS)];
+    pub fn get_stars(
+        &self,
+        calendar: &EventCalendar,
+        as_of: Option<Timestamp>,
+    ) -> Vec<CompletionLevel> {
+        let mut stars = vec![0; usize::from(calendar.num_days())];
         for (&(day, _), _) in self.completed.iter().filter(|&(_, ts)| {
             as_of.map(|timestamp| *ts <= timestamp).unwrap_or(true)
         }) {
-            if day > 0 && day <= NUM_PUZZLE_DAYS {
+            if calendar.has_day(day) {
                 stars[usize::from(day - 1)] += 1;
             }
         }
@@ -129,9 +122,45 @@ impl Member {
         self.completed.insert(puzzle_id, timestamp);
     }
 
+    // Merge another copy of the same member, e.g. from a different
+    // leaderboard whose snapshot may be older than this one
+    fn merge(&mut self, other: Member) {
+        if self.anonymous && !other.anonymous {
+            self.name = other.name;
+            self.anonymous = false;
+        }
+        for (puzzle_id, timestamp) in other.completed {
+            self.completed
+                .entry(puzzle_id)
+                .and_modify(|ts| *ts = (*ts).min(timestamp))
+                .or_insert(timestamp);
+        }
+    }
+
     pub fn iter_completed(&self) -> Iter<PuzzleId, Timestamp> {
         self.completed.iter()
     }
+
+    pub fn get_star_ts(
+        &self,
+        puzzle_id: PuzzleId,
+        as_of: Option<Timestamp>,
+    ) -> Option<Timestamp> {
+        self.completed.get(&puzzle_id).copied().filter(|&ts| {
+            as_of.map(|timestamp| ts <= timestamp).unwrap_or(true)
+        })
+    }
+
+    // Time from the first to the second star of the day, if both were got
+    pub fn get_part2_delta(
+        &self,
+        day: PuzzleDay,
+        as_of: Option<Timestamp>,
+    ) -> Option<Timestamp> {
+        let part1_ts = self.get_star_ts((day, 1), as_of)?;
+        let part2_ts = self.get_star_ts((day, 2), as_of)?;
+        Some(part2_ts - part1_ts)
+    }
 }
 
 impl Ord for Member {
@@ -159,95 +188,687 @@ impl Hash for Member {
 }
 
 #[derive(Debug)]
-pub struct ResponseFormatError {
-    error: String,
+pub enum FetchError {
+    InvalidSessionCookie {
+        leaderboard_id: String,
+    },
+    Network {
+        leaderboard_id: String,
+        error: reqwest::Error,
+    },
+    InvalidLeaderboard {
+        year: EventYear,
+        leaderboard_id: String,
+    },
+    Unauthorized {
+        leaderboard_id: String,
+    },
+    RateLimited {
+        leaderboard_id: String,
+    },
+    Upstream {
+        leaderboard_id: String,
+        status: StatusCode,
+    },
+    UnexpectedStatus {
+        leaderboard_id: String,
+        status: StatusCode,
+    },
+    MalformedResponse {
+        leaderboard_id: String,
+        error: String,
+    },
+    Fixture {
+        path: PathBuf,
+        error: io::Error,
+    },
 }
 
-impl ResponseFormatError {
-    fn new(error: String) -> Self {
-        Self { error }
+impl FetchError {
+    // Connection errors and server errors may go away on their own, unlike
+    // client errors such as a missing leaderboard or an expired cookie
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Network { .. } | Self::Upstream { .. })
     }
 }
 
-impl Error for ResponseFormatError {
+impl Error for FetchError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match self {
+            Self::Network { error, .. } => Some(error),
+            Self::Fixture { error, .. } => Some(error),
+            _ => None,
+        }
     }
 }
 
-impl Display for ResponseFormatError {
+impl Display for FetchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "Response format error: {}", self.error)
+        match self {
+            Self::InvalidSessionCookie { leaderboard_id } => write!(
+                f,
+                "session cookie for leaderboard {} is not a valid header value",
+                leaderboard_id
+            ),
+            Self::Network {
+                leaderboard_id,
+                error,
+            } => write!(
+                f,
+                "failed to fetch leaderboard {}: {}",
+                leaderboard_id, error
+            ),
+            Self::InvalidLeaderboard {
+                year,
+                leaderboard_id,
+            } => write!(
+                f,
+                "leaderboard {} not found for {} event",
+                leaderboard_id, year
+            ),
+            Self::Unauthorized { leaderboard_id } => write!(
+                f,
+                "not authorized to fetch leaderboard {}, session cookie may \
+                be expired or lack access to it",
+                leaderboard_id
+            ),
+            Self::RateLimited { leaderboard_id } => write!(
+                f,
+                "rate limited while fetching leaderboard {}",
+                leaderboard_id
+            ),
+            Self::Upstream {
+                leaderboard_id,
+                status,
+            } => write!(
+                f,
+                "server error while fetching leaderboard {}: {}",
+                leaderboard_id, status
+            ),
+            Self::UnexpectedStatus {
+                leaderboard_id,
+                status,
+            } => write!(
+                f,
+                "unexpected response while fetching leaderboard {}: {}",
+                leaderboard_id, status
+            ),
+            Self::MalformedResponse {
+                leaderboard_id,
+                error,
+            } => write!(
+                f,
+                "malformed response for leaderboard {}: {}",
+                leaderboard_id, error
+            ),
+            Self::Fixture { path, error } => {
+                write!(f, "failed to read {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+pub struct FetchedMembers {
+    pub members: HashSet<Member>,
+    pub failed_leaderboards: Vec<String>,
+}
+
+pub enum MemberSource {
+    Remote {
+        aoc_base_url: String,
+        // Session cookie of each leaderboard, keyed by leaderboard ID
+        session_cookies: HashMap<String, String>,
+        fetcher: Arc<Fetcher>,
+    },
+    Fixtures(PathBuf),
+}
+
+impl MemberSource {
+    pub fn get_members(
+        &self,
+        year: i32,
+        leaderboard_ids: &[String],
+        exclude_members: &HashSet<MemberId>,
+    ) -> Result<FetchedMembers, FetchError> {
+        match self {
+            Self::Remote {
+                aoc_base_url,
+                session_cookies,
+                fetcher,
+            } => fetcher.fetch_members(
+                aoc_base_url,
+                year,
+                leaderboard_ids,
+                exclude_members,
+                session_cookies,
+            ),
+            Self::Fixtures(fixtures_dir) => load_members(
+                fixtures_dir,
+                year,
+                leaderboard_ids,
+                exclude_members,
+            ),
+        }
+    }
+
+    pub fn fetcher(&self) -> Option<Arc<Fetcher>> {
+        match self {
+            Self::Remote { fetcher, .. } => Some(fetcher.clone()),
+            Self::Fixtures(_) => None,
+        }
+    }
+
+    // When the leaderboards may be fetched again, if any of them was fetched
+    // too recently
+    pub fn not_before(
+        &self,
+        year: i32,
+        leaderboard_ids: &[String],
+    ) -> Option<Instant> {
+        match self {
+            Self::Remote {
+                aoc_base_url,
+                fetcher,
+                ..
+            } => fetcher.not_before(aoc_base_url, year, leaderboard_ids),
+            Self::Fixtures(_) => None,
+        }
+    }
+
+    // Where members come from, which tells apart events fetched from
+    // different sources
+    pub fn origin(&self) -> String {
+        match self {
+            Self::Remote { aoc_base_url, .. } => aoc_base_url.clone(),
+            Self::Fixtures(fixtures_dir) => {
+                format!("fixtures:{}", fixtures_dir.display())
+            }
+        }
+    }
+
+    pub fn session_cookie(&self, leaderboard_id: &str) -> Option<&str> {
+        match self {
+            Self::Remote {
+                session_cookies, ..
+            } => session_cookies.get(leaderboard_id).map(String::as_str),
+            Self::Fixtures(_) => None,
+        }
+    }
+
+    // Members of a single leaderboard, without excluding any of them
+    pub fn get_leaderboard_members(
+        &self,
+        year: i32,
+        leaderboard_id: &str,
+    ) -> Result<HashSet<Member>, FetchError> {
+        match self {
+            Self::Remote {
+                aoc_base_url,
+                session_cookies,
+                fetcher,
+            } => fetcher.fetch_leaderboard_members(
+                aoc_base_url,
+                year,
+                leaderboard_id,
+                session_cookies
+                    .get(leaderboard_id)
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+            ),
+            Self::Fixtures(fixtures_dir) => load_leaderboard(
+                leaderboard_id,
+                &fixture_path(fixtures_dir, year, leaderboard_id),
+            ),
+        }
+    }
+}
+
+// Long-lived runtime and HTTP client shared by all fetches, so that
+// connections to Advent of Code are pooled and reused
+pub struct Fetcher {
+    runtime: Runtime,
+    client: Client,
+    throttle: Throttle,
+    retry_policy: RetryPolicy,
+}
+
+impl Fetcher {
+    pub fn new(
+        timeout: Duration,
+        throttle: Throttle,
+        retry_policy: RetryPolicy,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut headers = HeaderMap::new();
+        let user_agent = format!("{PKG_REPO} {PKG_VERSION}");
+        let user_agent_header = HeaderValue::from_str(&user_agent).unwrap();
+        headers.insert(USER_AGENT, user_agent_header);
+
+        let client = Client::builder()
+            .default_headers(headers)
+            .redirect(Policy::none())
+            .timeout(timeout)
+            .build()?;
+        let runtime = runtime::Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .thread_name("fetcher")
+            .build()?;
+
+        Ok(Self {
+            runtime,
+            client,
+            throttle,
+            retry_policy,
+        })
+    }
+
+    pub fn fetch_members(
+        &self,
+        aoc_base_url: &str,
+        year: i32,
+        leaderboard_ids: &[String],
+        exclude_members: &HashSet<MemberId>,
+        session_cookies: &HashMap<String, String>,
+    ) -> Result<FetchedMembers, FetchError> {
+        let responses = self.runtime.handle().block_on(join_all(
+            leaderboard_ids.iter().map(|leaderboard_id| {
+                let url = leaderboard_url(aoc_base_url, year, leaderboard_id);
+                let session_cookie = session_cookies
+                    .get(leaderboard_id)
+                    .map(String::as_str)
+                    .unwrap_or_default();
+                self.fetch_with_retries(
+                    url,
+                    year,
+                    leaderboard_id,
+                    session_cookie,
+                )
+            }),
+        ));
+
+        merge_members(
+            year,
+            leaderboard_ids.iter().zip(responses),
+            exclude_members,
+        )
+    }
+
+    // When all of the leaderboards may be fetched again, if any of them was
+    // fetched too recently
+    pub fn not_before(
+        &self,
+        aoc_base_url: &str,
+        year: i32,
+        leaderboard_ids: &[String],
+    ) -> Option<Instant> {
+        leaderboard_ids
+            .iter()
+            .filter_map(|leaderboard_id| {
+                self.throttle.not_before(&leaderboard_url(
+                    aoc_base_url,
+                    year,
+                    leaderboard_id,
+                ))
+            })
+            .max()
+    }
+
+    pub fn fetch_leaderboard_members(
+        &self,
+        aoc_base_url: &str,
+        year: i32,
+        leaderboard_id: &str,
+        session_cookie: &str,
+    ) -> Result<HashSet<Member>, FetchError> {
+        let url = leaderboard_url(aoc_base_url, year, leaderboard_id);
+        self.runtime.handle().block_on(self.fetch_with_retries(
+            url,
+            year,
+            leaderboard_id,
+            session_cookie,
+        ))
+    }
+
+    async fn fetch_with_retries(
+        &self,
+        url: String,
+        year: i32,
+        leaderboard_id: &str,
+        session_cookie: &str,
+    ) -> Result<HashSet<Member>, FetchError> {
+        let cookie = HeaderValue::from_str(session_cookie).map_err(|_| {
+            FetchError::InvalidSessionCookie {
+                leaderboard_id: leaderboard_id.to_string(),
+            }
+        })?;
+
+        let mut attempt = 1;
+        loop {
+            self.throttle.acquire(&url).await;
+            info!("Fetching {} (attempt {})", url, attempt);
+            let request = self.client.get(&url).header(COOKIE, &cookie);
+            match fetch_leaderboard(year, leaderboard_id, request).await {
+                Ok(members) => return Ok(members),
+                Err(err)
+                    if err.is_transient()
+                        && attempt <= self.retry_policy.max_retries =>
+                {
+                    let delay = self.retry_policy.delay(attempt);
+                    warn!(
+                        "Attempt {} to fetch leaderboard {} failed: {}, \
+                        retrying in {} ms",
+                        attempt,
+                        leaderboard_id,
+                        err,
+                        delay.as_millis()
+                    );
+                    delay_for(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    if attempt > 1 {
+                        warn!(
+                            "Giving up on leaderboard {} after {} attempts",
+                            leaderboard_id, attempt
+                        );
+                    }
+                    return Err(err);
+                }
+            }
+        }
     }
 }
 
-#[tokio::main]
-pub async fn fetch_members(
+// Retries transient fetch failures, waiting exponentially longer after each
+// attempt, plus some random jitter so that retries don't happen in lockstep
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+        }
+    }
+
+    // Random delay between half and all of the exponential backoff
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_RETRY_DELAY);
+        backoff / 2 + backoff.mul_f64(thread_rng().gen_range(0.0..=0.5))
+    }
+}
+
+// Limits requests to Advent of Code, both per leaderboard URL and overall.
+// Leaderboards are not fetched again until their URL may be requested again,
+// see `not_before`, whereas requests over the overall limit wait until they
+// are allowed.
+pub struct Throttle {
+    min_interval: Duration,
+    max_requests_per_minute: usize,
+    state: Mutex<ThrottleState>,
+}
+
+#[derive(Default)]
+struct ThrottleState {
+    last_fetched: HashMap<String, Instant>,
+    recent_requests: VecDeque<Instant>,
+}
+
+impl Throttle {
+    pub fn new(min_interval: Duration, max_requests_per_minute: usize) -> Self {
+        Self {
+            min_interval,
+            max_requests_per_minute,
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    async fn acquire(&self, url: &str) {
+        loop {
+            let wait = {
+                // TODO: handle LockResult errors
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                while state
+                    .recent_requests
+                    .front()
+                    .filter(|&&sent_at| now - sent_at >= THROTTLE_WINDOW)
+                    .is_some()
+                {
+                    state.recent_requests.pop_front();
+                }
+
+                let wait = state
+                    .recent_requests
+                    .front()
+                    .filter(|_| {
+                        state.recent_requests.len()
+                            >= self.max_requests_per_minute
+                    })
+                    .map(|&sent_at| {
+                        (sent_at + THROTTLE_WINDOW)
+                            .saturating_duration_since(now)
+                    })
+                    .unwrap_or(Duration::ZERO);
+                if wait.is_zero() {
+                    // Every attempt counts, whether it succeeds or not
+                    state.recent_requests.push_back(now);
+                    state.last_fetched.insert(url.to_string(), now);
+                    debug!(
+                        "Throttle: {} of {} requests in the last minute",
+                        state.recent_requests.len(),
+                        self.max_requests_per_minute
+                    );
+                    return;
+                }
+                info!(
+                    "Throttle: delaying {} by {} seconds ({} of {} requests \
+                    in the last minute)",
+                    url,
+                    wait.as_secs(),
+                    state.recent_requests.len(),
+                    self.max_requests_per_minute
+                );
+                wait
+            };
+            delay_for(wait).await;
+        }
+    }
+
+    // When the URL may be requested again, if it was requested too recently
+    fn not_before(&self, url: &str) -> Option<Instant> {
+        // TODO: handle LockResult errors
+        self.state
+            .lock()
+            .unwrap()
+            .last_fetched
+            .get(url)
+            .map(|&fetched_at| fetched_at + self.min_interval)
+            .filter(|&allowed_at| allowed_at > Instant::now())
+    }
+}
+
+pub fn load_members(
+    fixtures_dir: &Path,
     year: i32,
     leaderboard_ids: &[String],
     exclude_members: &HashSet<MemberId>,
-    session_cookie: &str,
-) -> Result<HashSet<Member>, Box<dyn Error>> {
-    let mut headers = HeaderMap::new();
-    headers.insert(COOKIE, HeaderValue::from_str(session_cookie)?);
-
-    let user_agent = format!("{PKG_REPO} {PKG_VERSION}");
-    let user_agent_header = HeaderValue::from_str(&user_agent).unwrap();
-    headers.insert(USER_AGENT, user_agent_header);
-
-    let client = Client::builder()
-        .default_headers(headers)
-        .redirect(Policy::none())
-        .build()?;
-
-    let responses = join_all(leaderboard_ids.iter().map(|leaderboard_id| {
-        let url = format!(
-            "https://adventofcode.com/{}/leaderboard/private/view/{}.json",
-            year, leaderboard_id
-        );
-        info!("Fetching {}", url);
-        fetch_leaderboard(client.get(&url))
-    }))
-    .await;
-
-    let mut all_members = HashSet::new();
-    for resp in responses {
-        let mut members = resp?;
+) -> Result<FetchedMembers, FetchError> {
+    let responses = leaderboard_ids.iter().map(|leaderboard_id| {
+        let path = fixture_path(fixtures_dir, year, leaderboard_id);
+        info!("Loading {}", path.display());
+        (leaderboard_id, load_leaderboard(leaderboard_id, &path))
+    });
+
+    merge_members(year, responses, exclude_members)
+}
+
+fn leaderboard_url(
+    aoc_base_url: &str,
+    year: i32,
+    leaderboard_id: &str,
+) -> String {
+    format!(
+        "{}/{}/leaderboard/private/view/{}.json",
+        aoc_base_url, year, leaderboard_id
+    )
+}
+
+fn fixture_path(
+    fixtures_dir: &Path,
+    year: i32,
+    leaderboard_id: &str,
+) -> PathBuf {
+    fixtures_dir
+        .join(year.to_string())
+        .join(format!("{}.json", leaderboard_id))
+}
+
+fn merge_members<'a, I>(
+    year: i32,
+    responses: I,
+    exclude_members: &HashSet<MemberId>,
+) -> Result<FetchedMembers, FetchError>
+where
+    I: IntoIterator<Item = (&'a String, Result<HashSet<Member>, FetchError>)>,
+{
+    let mut all_members: HashMap<MemberId, Member> = HashMap::new();
+    let mut failed_leaderboards = Vec::new();
+    let mut last_error = None;
+    let mut fetched_count = 0;
+    for (leaderboard_id, resp) in responses {
+        let members = match resp {
+            Ok(members) => members,
+            Err(err) => {
+                warn!("Skipping leaderboard {}: {}", leaderboard_id, err);
+                failed_leaderboards.push(leaderboard_id.clone());
+                last_error = Some(err);
+                continue;
+            }
+        };
         info!("Fetched {} members", members.len());
-        all_members.extend(
-            members.drain().filter(|m| !exclude_members.contains(&m.id)),
-        )
+        fetched_count += 1;
+        for member in members {
+            if exclude_members.contains(&member.id) {
+                continue;
+            }
+            match all_members.entry(member.id) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(member),
+                Entry::Vacant(entry) => {
+                    entry.insert(member);
+                }
+            }
+        }
+    }
+
+    // Only give up on the event if none of the leaderboards could be fetched
+    if let Some(err) = last_error.filter(|_| fetched_count == 0) {
+        return Err(err);
     }
 
     let star_count: usize = all_members
-        .iter()
+        .values()
         .map(|member| member.completed.len())
         .sum();
     info!("{} unique members found", all_members.len());
     info!("{} stars collected in {} event", star_count, year);
-    Ok(all_members)
+    Ok(FetchedMembers {
+        members: all_members.into_values().collect(),
+        failed_leaderboards,
+    })
 }
 
 async fn fetch_leaderboard(
+    year: i32,
+    leaderboard_id: &str,
     request: RequestBuilder,
-) -> Result<HashSet<Member>, Box<dyn Error>> {
-    request
-        .send()
-        .await?
+) -> Result<HashSet<Member>, FetchError> {
+    let started_at = Instant::now();
+    let response =
+        request.send().await.map_err(|error| FetchError::Network {
+            leaderboard_id: leaderboard_id.to_string(),
+            error,
+        })?;
+    debug!(
+        "Leaderboard {} responded with {} in {} ms",
+        leaderboard_id,
+        response.status(),
+        started_at.elapsed().as_millis()
+    );
+
+    // An expired or invalid session cookie gets redirected to the login page
+    let status = response.status();
+    if status.is_redirection()
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+    {
+        return Err(FetchError::Unauthorized {
+            leaderboard_id: leaderboard_id.to_string(),
+        });
+    } else if status == StatusCode::NOT_FOUND {
+        return Err(FetchError::InvalidLeaderboard {
+            year,
+            leaderboard_id: leaderboard_id.to_string(),
+        });
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(FetchError::RateLimited {
+            leaderboard_id: leaderboard_id.to_string(),
+        });
+    } else if status.is_server_error() {
+        return Err(FetchError::Upstream {
+            leaderboard_id: leaderboard_id.to_string(),
+            status,
+        });
+    } else if !status.is_success() {
+        return Err(FetchError::UnexpectedStatus {
+            leaderboard_id: leaderboard_id.to_string(),
+            status,
+        });
+    }
+
+    response
         .json::<Value>()
-        .await?
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_leaderboard(&value))
+        .map_err(|error| FetchError::MalformedResponse {
+            leaderboard_id: leaderboard_id.to_string(),
+            error,
+        })
+}
+
+fn load_leaderboard(
+    leaderboard_id: &str,
+    path: &Path,
+) -> Result<HashSet<Member>, FetchError> {
+    let contents =
+        fs::read_to_string(path).map_err(|error| FetchError::Fixture {
+            path: path.to_path_buf(),
+            error,
+        })?;
+    serde_json::from_str::<Value>(&contents)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_leaderboard(&value))
+        .map_err(|error| FetchError::MalformedResponse {
+            leaderboard_id: leaderboard_id.to_string(),
+            error,
+        })
+}
+
+pub fn parse_leaderboard(value: &Value) -> Result<HashSet<Member>, String> {
+    value
         .get("members")
         .and_then(|val| val.as_object())
         .map(|obj| obj.values())
-        .ok_or_else(|| {
-            Box::new(ResponseFormatError::new(
-                "'members' field missing or not an object".to_string(),
-            ))
-        })?
-        .map(|value| {
-            Member::try_from(value)
-                .map_err(|err| Box::new(ResponseFormatError::new(err)) as _)
-        })
+        .ok_or_else(|| "'members' field missing or not an object".to_string())?
+        .map(Member::try_from)
         .collect::<Result<HashSet<_>, _>>()
 }
 
@@ -308,3 +929,139 @@ impl TryFrom<&Value> for Member {
         Ok(member)
     }
 }
+
+impl From<&Member> for Value {
+    fn from(member: &Member) -> Self {
+        let mut completed = Map::new();
+        for (&(day, part), &timestamp) in member.completed.iter() {
+            if let Value::Object(parts) = completed
+                .entry(day.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                parts.insert(
+                    part.to_string(),
+                    json!({ "get_star_ts": timestamp }),
+                );
+            }
+        }
+        let name = (!member.anonymous).then_some(&member.name);
+        json!({
+            "id": member.id,
+            "name": name,
+            "completion_day_level": completed,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn member(
+        id: MemberId,
+        name: Option<&str>,
+        stars: &[(PuzzleId, Timestamp)],
+    ) -> Member {
+        let mut member = Member::new(id, name.map(String::from));
+        for &(puzzle_id, timestamp) in stars {
+            member.add_star(puzzle_id, timestamp);
+        }
+        member
+    }
+
+    #[test]
+    fn merge_keeps_earliest_star_of_each_puzzle() {
+        let mut merged = member(1, None, &[((1, 1), 100), ((1, 2), 300)]);
+        merged.merge(member(1, None, &[((1, 2), 200), ((2, 1), 400)]));
+        assert_eq!(merged.get_star_ts((1, 1), None), Some(100));
+        assert_eq!(merged.get_star_ts((1, 2), None), Some(200));
+        assert_eq!(merged.get_star_ts((2, 1), None), Some(400));
+        assert_eq!(merged.star_count(None), 3);
+    }
+
+    #[test]
+    fn merge_prefers_named_copy() {
+        let mut merged = member(1, None, &[]);
+        merged.merge(member(1, Some("Alice"), &[]));
+        assert_eq!(merged.get_name(), "Alice");
+        merged.merge(member(1, None, &[]));
+        assert_eq!(merged.get_name(), "Alice");
+        merged.merge(member(1, Some("Bob"), &[]));
+        assert_eq!(merged.get_name(), "Alice");
+    }
+
+    #[test]
+    fn members_round_trip_through_json() {
+        let members = [
+            member(10, Some("Alice"), &[((1, 1), 100), ((1, 2), 200)]),
+            member(20, None, &[((2, 1), 300)]),
+        ];
+        let members_value = members
+            .iter()
+            .map(|member| (member.get_id().to_string(), Value::from(member)))
+            .collect::<Map<_, _>>();
+        let parsed =
+            parse_leaderboard(&json!({ "members": members_value })).unwrap();
+        assert_eq!(parsed.len(), members.len());
+        for member in members.iter() {
+            let copy = parsed.get(member).unwrap();
+            assert_eq!(copy.name, member.name);
+            assert_eq!(copy.anonymous, member.anonymous);
+            assert_eq!(copy.completed, member.completed);
+        }
+    }
+
+    #[test]
+    fn merge_members_skips_failed_leaderboards() {
+        let ids = ["1".to_string(), "2".to_string(), "3".to_string()];
+        let responses = vec![
+            (
+                &ids[0],
+                Ok(HashSet::from([
+                    member(10, Some("Alice"), &[((1, 1), 100)]),
+                    member(20, None, &[((1, 1), 150)]),
+                ])),
+            ),
+            (
+                &ids[1],
+                Err(FetchError::InvalidLeaderboard {
+                    year: 2022,
+                    leaderboard_id: ids[1].clone(),
+                }),
+            ),
+            (
+                &ids[2],
+                Ok(HashSet::from([
+                    member(10, Some("Alice"), &[((1, 1), 90)]),
+                    member(30, Some("Carol"), &[((1, 1), 200)]),
+                ])),
+            ),
+        ];
+        let fetched =
+            merge_members(2022, responses, &HashSet::from([30])).unwrap();
+        assert_eq!(fetched.failed_leaderboards, vec!["2".to_string()]);
+        let mut members = fetched.members.into_iter().collect::<Vec<_>>();
+        members.sort_unstable_by_key(Member::get_id);
+        assert_eq!(
+            members.iter().map(Member::get_id).collect::<Vec<_>>(),
+            vec![10, 20]
+        );
+        assert_eq!(members[0].get_star_ts((1, 1), None), Some(90));
+    }
+
+    #[test]
+    fn merge_members_fails_if_no_leaderboard_was_fetched() {
+        let ids = ["1".to_string()];
+        let responses = vec![(
+            &ids[0],
+            Err(FetchError::InvalidLeaderboard {
+                year: 2022,
+                leaderboard_id: ids[0].clone(),
+            }),
+        )];
+        assert!(matches!(
+            merge_members(2022, responses, &HashSet::new()),
+            Err(FetchError::InvalidLeaderboard { .. })
+        ));
+    }
+}
@@ -1,16 +1,50 @@
-use crate::aoc::{EventYear, MemberId};
-use crate::leaders::LeaderboardOrder;
-use config::{Config, ConfigError, File, Value};
+use crate::aoc::*;
+use crate::clock::Clock;
+use crate::leaders::{EventConfig, LeaderboardOrder};
+use crate::scoring::ScoringConfig;
+use chrono::{DateTime, FixedOffset};
+use config::{Config, ConfigError, Environment, File, Value};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const ENV_PREFIX: &str = "AOCLB";
+const COOKIE_SETTINGS: [&str; 4] = [
+    "session_cookie",
+    "session_cookie_ref",
+    "session_cookie_file",
+    "session_cookie_env",
+];
 
 pub struct AppSettings {
+    pub aoc_base_url: String,
     pub leaderboard_name: String,
     pub leaderboard_ids: Vec<String>,
+    pub year_leaderboard_ids: HashMap<EventYear, Vec<String>>,
     pub leaderboard_default_order: LeaderboardOrder,
+    pub unsolved_star_penalty_sec: u64,
     pub leaderboard_update_sec: u64,
+    pub past_event_update_sec: u64,
+    pub refresh_concurrency: usize,
+    pub fetch_timeout_sec: u64,
+    pub min_fetch_interval_sec: u64,
+    pub max_requests_per_minute: usize,
+    pub fetch_retries: u32,
+    pub fetch_retry_delay_ms: u64,
     pub exclude_members: Vec<MemberId>,
     pub session_cookie: String,
+    pub leaderboard_cookies: HashMap<String, String>,
     pub latest_event_year: Option<EventYear>,
+    pub fixtures_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub simulated_now: Option<DateTime<FixedOffset>>,
+    pub freeze_simulated_now: bool,
+    pub clock: Clock,
 }
 
 impl AppSettings {
@@ -18,31 +52,125 @@ impl AppSettings {
         let mut settings = Config::default();
 
         // Set default values
+        settings.set_default("aoc_base_url", "https://adventofcode.com")?;
         settings.set_default("leaderboard_update_sec", 15 * 60)?;
+        settings.set_default("past_event_update_sec", 24 * 60 * 60)?;
+        settings.set_default("refresh_concurrency", 4)?;
+        settings.set_default("fetch_timeout_sec", 30)?;
+        settings.set_default("min_fetch_interval_sec", 15 * 60)?;
+        settings.set_default("max_requests_per_minute", 30)?;
+        settings.set_default("fetch_retries", 3)?;
+        settings.set_default("fetch_retry_delay_ms", 1000)?;
         settings.set_default("leaderboard_default_order", "local_score")?;
+        settings.set_default("unsolved_star_penalty_sec", 24 * 60 * 60)?;
         settings.set_default("exclude_members", Vec::<Value>::new())?;
+        settings.set_default("freeze_simulated_now", false)?;
 
-        // Load settings from file
+        // Load settings from file, overridden by environment variables such
+        // as AOCLB_SESSION_COOKIE
         settings.merge(File::with_name(filename))?;
+        settings.merge(Environment::with_prefix(ENV_PREFIX))?;
 
         // Required settings
         let leaderboard_name = settings.get_str("leaderboard_name")?;
-        let leaderboard_ids = settings
-            .get_array("leaderboard_ids")?
-            .into_iter()
-            .map(|v| v.into_str())
-            .collect::<Result<Vec<_>, _>>()?;
 
-        // TODO: load session cookie from different file?
-        let session_cookie = settings.get_str("session_cookie")?;
+        // Leaderboards may have their own session cookie, either inline, as a
+        // reference to one of the named session_cookies, or read from a file
+        // or an environment variable
+        let named_cookies = match settings.get_table("session_cookies") {
+            Ok(table) => table,
+            Err(ConfigError::NotFound(_)) => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        let mut leaderboard_cookies = HashMap::new();
+        let leaderboard_ids = parse_leaderboard_ids(
+            settings.get_array("leaderboard_ids")?,
+            &named_cookies,
+            &mut leaderboard_cookies,
+        )?;
+
+        // Events may be made of a different set of leaderboards, e.g.
+        // [years.2021] leaderboard_ids = [...]
+        let years = match settings.get_table("years") {
+            Ok(table) => table,
+            Err(ConfigError::NotFound(_)) => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        let mut year_leaderboard_ids = HashMap::new();
+        for (year, year_settings) in years {
+            let year = year.parse::<EventYear>().map_err(|_| {
+                ConfigError::Message(format!("invalid event year {}", year))
+            })?;
+            if let Some(ids) =
+                year_settings.into_table()?.remove("leaderboard_ids")
+            {
+                year_leaderboard_ids.insert(
+                    year,
+                    parse_leaderboard_ids(
+                        ids.into_array()?,
+                        &named_cookies,
+                        &mut leaderboard_cookies,
+                    )?,
+                );
+            }
+        }
+
+        // Leaderboards loaded from fixture files need no session cookie
+        let fixtures_dir = match settings.get_str("fixtures_dir") {
+            Ok(dir) => Some(PathBuf::from(dir)),
+            Err(ConfigError::NotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+
+        // The default session cookie is only needed for leaderboards without
+        // their own, and may be kept in a separate file, e.g. a Docker secret
+        let session_cookie = match settings.get_str("session_cookie") {
+            Err(ConfigError::NotFound(_)) => {
+                match settings.get_str("session_cookie_file") {
+                    Ok(path) => read_secret_file(&path)?,
+                    Err(ConfigError::NotFound(_))
+                        if fixtures_dir.is_some()
+                            || leaderboard_ids
+                                .iter()
+                                .chain(year_leaderboard_ids.values().flatten())
+                                .all(|id| {
+                                    leaderboard_cookies.contains_key(id)
+                                }) =>
+                    {
+                        String::new()
+                    }
+                    Err(ConfigError::NotFound(_)) => {
+                        return Err(ConfigError::Message(
+                            "session_cookie or session_cookie_file is required"
+                                .to_string(),
+                        ))
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            result => result?,
+        };
 
         // Optional settings
+        let aoc_base_url = settings
+            .get_str("aoc_base_url")?
+            .trim_end_matches('/')
+            .to_string();
         let leaderboard_default_order =
             settings.get("leaderboard_default_order").map_err(|_| {
                 ConfigError::Message(
                     "invalid leaderboard_default_order".to_string(),
                 )
             })?;
+        let unsolved_star_penalty_sec = settings
+            .get_int("unsolved_star_penalty_sec")?
+            .try_into()
+            .map_err(|_| {
+                ConfigError::Message(
+                    "unsolved_star_penalty_sec must not be negative"
+                        .to_string(),
+                )
+            })?;
         let leaderboard_update_sec = settings
             .get_int("leaderboard_update_sec")?
             .try_into()
@@ -51,11 +179,77 @@ impl AppSettings {
                     "leaderboard_update_sec must not be negative".to_string(),
                 )
             })?;
+        let past_event_update_sec = settings
+            .get_int("past_event_update_sec")?
+            .try_into()
+            .map_err(|_| {
+                ConfigError::Message(
+                    "past_event_update_sec must not be negative".to_string(),
+                )
+            })?;
+        let refresh_concurrency = settings
+            .get_int("refresh_concurrency")?
+            .try_into()
+            .ok()
+            .filter(|&value: &usize| value > 0)
+            .ok_or_else(|| {
+                ConfigError::Message(
+                    "refresh_concurrency must be greater than zero".to_string(),
+                )
+            })?;
+        let fetch_timeout_sec = settings
+            .get_int("fetch_timeout_sec")?
+            .try_into()
+            .ok()
+            .filter(|&value: &u64| value > 0)
+            .ok_or_else(|| {
+                ConfigError::Message(
+                    "fetch_timeout_sec must be greater than zero".to_string(),
+                )
+            })?;
+        let min_fetch_interval_sec = settings
+            .get_int("min_fetch_interval_sec")?
+            .try_into()
+            .map_err(|_| {
+                ConfigError::Message(
+                    "min_fetch_interval_sec must not be negative".to_string(),
+                )
+            })?;
+        let max_requests_per_minute = settings
+            .get_int("max_requests_per_minute")?
+            .try_into()
+            .ok()
+            .filter(|&value: &usize| value > 0)
+            .ok_or_else(|| {
+                ConfigError::Message(
+                    "max_requests_per_minute must be greater than zero"
+                        .to_string(),
+                )
+            })?;
+        let fetch_retries =
+            settings.get_int("fetch_retries")?.try_into().map_err(|_| {
+                ConfigError::Message(
+                    "fetch_retries must not be negative".to_string(),
+                )
+            })?;
+        let fetch_retry_delay_ms = settings
+            .get_int("fetch_retry_delay_ms")?
+            .try_into()
+            .map_err(|_| {
+                ConfigError::Message(
+                    "fetch_retry_delay_ms must not be negative".to_string(),
+                )
+            })?;
         let exclude_members = settings
             .get_array("exclude_members")?
             .into_iter()
             .map(|v| v.try_into())
             .collect::<Result<Vec<_>, _>>()?;
+        let cache_dir = match settings.get_str("cache_dir") {
+            Ok(dir) => Some(PathBuf::from(dir)),
+            Err(ConfigError::NotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
         let latest_event_year =
             settings.get_int("latest_event_year").map_or_else(
                 |err| match err {
@@ -74,15 +268,212 @@ impl AppSettings {
                         .map(Some)
                 },
             )?;
+        let simulated_now = match settings.get_str("simulated_now") {
+            Ok(value) => {
+                Some(DateTime::parse_from_rfc3339(&value).map_err(|err| {
+                    ConfigError::Message(format!(
+                        "invalid simulated_now: {}",
+                        err
+                    ))
+                })?)
+            }
+            Err(ConfigError::NotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+        let freeze_simulated_now = settings.get_bool("freeze_simulated_now")?;
+        let clock = simulated_now.map_or(Clock::System, |now| {
+            Clock::simulated(now.into(), freeze_simulated_now)
+        });
 
         Ok(Self {
+            aoc_base_url,
             leaderboard_name,
             leaderboard_ids,
+            year_leaderboard_ids,
             leaderboard_default_order,
+            unsolved_star_penalty_sec,
             leaderboard_update_sec,
+            past_event_update_sec,
+            refresh_concurrency,
+            fetch_timeout_sec,
+            min_fetch_interval_sec,
+            max_requests_per_minute,
+            fetch_retries,
+            fetch_retry_delay_ms,
             exclude_members,
             session_cookie,
+            leaderboard_cookies,
             latest_event_year,
+            fixtures_dir,
+            cache_dir,
+            simulated_now,
+            freeze_simulated_now,
+            clock,
+        })
+    }
+
+    pub fn event_leaderboard_ids(&self, year: EventYear) -> &[String] {
+        self.year_leaderboard_ids
+            .get(&year)
+            .unwrap_or(&self.leaderboard_ids)
+    }
+
+    pub fn session_cookie(&self, leaderboard_id: &str) -> &str {
+        self.leaderboard_cookies
+            .get(leaderboard_id)
+            .unwrap_or(&self.session_cookie)
+    }
+
+    // Reuses the given fetcher, if any, so that its connections and throttle
+    // state carry over when settings are reloaded
+    pub fn member_source(
+        &self,
+        fetcher: Option<Arc<Fetcher>>,
+    ) -> Result<MemberSource, Box<dyn Error>> {
+        if let Some(dir) = &self.fixtures_dir {
+            return Ok(MemberSource::Fixtures(dir.clone()));
+        }
+
+        let fetcher = match fetcher {
+            Some(fetcher) => fetcher,
+            None => Arc::new(Fetcher::new(
+                Duration::from_secs(self.fetch_timeout_sec),
+                Throttle::new(
+                    Duration::from_secs(self.min_fetch_interval_sec),
+                    self.max_requests_per_minute,
+                ),
+                RetryPolicy::new(
+                    self.fetch_retries,
+                    Duration::from_millis(self.fetch_retry_delay_ms),
+                ),
+            )?),
+        };
+        Ok(MemberSource::Remote {
+            aoc_base_url: self.aoc_base_url.clone(),
+            session_cookies: self
+                .leaderboard_ids
+                .iter()
+                .chain(self.year_leaderboard_ids.values().flatten())
+                .map(|id| (id.clone(), self.session_cookie(id).to_string()))
+                .collect(),
+            fetcher,
         })
     }
+
+    pub fn event_config(&self, source: MemberSource) -> EventConfig {
+        EventConfig {
+            source,
+            leaderboard_ids: self.leaderboard_ids.clone(),
+            year_leaderboard_ids: self.year_leaderboard_ids.clone(),
+            update_sec: self.leaderboard_update_sec,
+            past_event_update_sec: self.past_event_update_sec,
+            exclude_members: self.exclude_members.iter().cloned().collect(),
+            clock: self.clock,
+            scoring: ScoringConfig {
+                unsolved_star_penalty_sec: self.unsolved_star_penalty_sec,
+            },
+        }
+    }
+}
+
+// Current settings, which are replaced as a whole when they are reloaded
+pub struct SettingsHandle {
+    current: RwLock<Arc<AppSettings>>,
+}
+
+impl SettingsHandle {
+    pub fn new(settings: AppSettings) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(settings)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<AppSettings> {
+        // TODO: handle LockResult errors
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, settings: AppSettings) {
+        // TODO: handle LockResult errors
+        *self.current.write().unwrap() = Arc::new(settings);
+    }
+}
+
+// Leaderboard IDs, each of which may be given as a table along with its own
+// session cookie
+fn parse_leaderboard_ids(
+    values: Vec<Value>,
+    named_cookies: &HashMap<String, Value>,
+    leaderboard_cookies: &mut HashMap<String, String>,
+) -> Result<Vec<String>, ConfigError> {
+    let mut leaderboard_ids = Vec::new();
+    for value in values {
+        let mut table = match value.clone().into_table() {
+            Ok(table) => table,
+            Err(_) => {
+                leaderboard_ids.push(value.into_str()?);
+                continue;
+            }
+        };
+        let id = table
+            .remove("id")
+            .ok_or_else(|| {
+                ConfigError::Message(
+                    "leaderboard_ids entries must have an id".to_string(),
+                )
+            })?
+            .into_str()?;
+        let mut cookie_settings = COOKIE_SETTINGS
+            .iter()
+            .filter_map(|&key| table.remove(key).map(|value| (key, value)))
+            .collect::<Vec<_>>();
+        if cookie_settings.len() > 1 {
+            return Err(ConfigError::Message(format!(
+                "leaderboard {} must have only one of {}",
+                id,
+                COOKIE_SETTINGS.join(", ")
+            )));
+        }
+        let cookie = match cookie_settings.pop() {
+            Some(("session_cookie_ref", name)) => {
+                let name = name.into_str()?;
+                let cookie =
+                    named_cookies.get(&name).cloned().ok_or_else(|| {
+                        ConfigError::Message(format!(
+                            "leaderboard {} refers to unknown session \
+                            cookie {}",
+                            id, name
+                        ))
+                    })?;
+                Some(cookie.into_str()?)
+            }
+            Some(("session_cookie_file", path)) => {
+                Some(read_secret_file(&path.into_str()?)?)
+            }
+            Some(("session_cookie_env", var)) => {
+                Some(read_secret_env(&var.into_str()?)?)
+            }
+            Some((_, cookie)) => Some(cookie.into_str()?),
+            None => None,
+        };
+        if let Some(cookie) = cookie {
+            leaderboard_cookies.insert(id.clone(), cookie);
+        }
+        leaderboard_ids.push(id);
+    }
+    Ok(leaderboard_ids)
+}
+
+fn read_secret_file(path: &str) -> Result<String, ConfigError> {
+    fs::read_to_string(path)
+        .map(|contents| contents.trim().to_string())
+        .map_err(|err| {
+            ConfigError::Message(format!("failed to read {}: {}", path, err))
+        })
+}
+
+fn read_secret_env(var: &str) -> Result<String, ConfigError> {
+    env::var(var).map_err(|err| {
+        ConfigError::Message(format!("failed to read ${}: {}", var, err))
+    })
 }
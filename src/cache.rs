@@ -0,0 +1,124 @@
+use crate::aoc::*;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct CachedEvent {
+    pub fetched: FetchedMembers,
+    pub updated_at: SystemTime,
+}
+
+// What an event was fetched with, which decides whether its cached copy can
+// still be used
+pub struct CacheKey<'a> {
+    pub source: &'a MemberSource,
+    pub leaderboard_ids: &'a [String],
+    pub exclude_members: &'a HashSet<MemberId>,
+}
+
+fn event_path(cache_dir: &Path, year: EventYear) -> PathBuf {
+    cache_dir.join(format!("{}.json", year))
+}
+
+pub fn save_event(
+    cache_dir: &Path,
+    year: EventYear,
+    key: &CacheKey,
+    members: &HashSet<Member>,
+    failed_leaderboards: &[String],
+    updated_at: SystemTime,
+) -> Result<(), Box<dyn Error>> {
+    let members = members
+        .iter()
+        .map(|member| (member.get_id().to_string(), Value::from(member)))
+        .collect::<Map<_, _>>();
+    let value = json!({
+        "source": key.source.origin(),
+        "leaderboard_ids": key.leaderboard_ids,
+        "exclude_members": key.exclude_members,
+        "updated_at": updated_at.duration_since(UNIX_EPOCH)?.as_secs(),
+        "failed_leaderboards": failed_leaderboards,
+        "members": members,
+    });
+
+    // Write to a temporary file first so that a crash never leaves a
+    // truncated cache file behind
+    fs::create_dir_all(cache_dir)?;
+    let path = event_path(cache_dir, year);
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec(&value)?)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+pub fn load_event(
+    cache_dir: &Path,
+    year: EventYear,
+    key: &CacheKey,
+) -> Result<Option<CachedEvent>, Box<dyn Error>> {
+    let contents = match fs::read_to_string(event_path(cache_dir, year)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let value = serde_json::from_str::<Value>(&contents)?;
+
+    // Events cached from a different source, e.g. from fixtures rather than
+    // from Advent of Code, or for a different set of leaderboards are of no
+    // use
+    let cached_source = value.get("source").and_then(|val| val.as_str());
+    if cached_source != Some(key.source.origin().as_str()) {
+        return Ok(None);
+    }
+
+    let cached_ids = value
+        .get("leaderboard_ids")
+        .and_then(|val| val.as_array())
+        .ok_or("'leaderboard_ids' missing or not an array")?
+        .iter()
+        .map(|val| val.as_str().unwrap_or_default())
+        .collect::<HashSet<_>>();
+    if cached_ids != key.leaderboard_ids.iter().map(String::as_str).collect() {
+        return Ok(None);
+    }
+
+    // Cached events lack the members that were excluded when they were
+    // fetched, so they are of no use once any of those is readmitted
+    let cached_excluded = value
+        .get("exclude_members")
+        .and_then(|val| val.as_array())
+        .map(|ids| ids.iter().filter_map(|val| val.as_i64()).collect())
+        .unwrap_or_else(HashSet::new);
+    if !cached_excluded.is_subset(key.exclude_members) {
+        return Ok(None);
+    }
+
+    let updated_at = value
+        .get("updated_at")
+        .and_then(|val| val.as_u64())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .ok_or("'updated_at' missing or invalid")?;
+    let failed_leaderboards = value
+        .get("failed_leaderboards")
+        .and_then(|val| val.as_array())
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|val| val.as_str())
+                .map(|id| id.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let members = parse_leaderboard(&value)?;
+
+    Ok(Some(CachedEvent {
+        fetched: FetchedMembers {
+            members,
+            failed_leaderboards,
+        },
+        updated_at,
+    }))
+}
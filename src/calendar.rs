@@ -0,0 +1,144 @@
+use crate::aoc::{EventYear, PuzzleDay, Timestamp};
+use crate::clock::Clock;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone};
+use std::ops::RangeInclusive;
+
+pub const EVENT_START_DAY: u32 = 1;
+pub const EVENT_START_MONTH: u32 = 12;
+pub const RELEASE_TIMEZONE_OFFSET: i32 = -5 * 3600;
+const PAST_EVENT_DAYS: i64 = 31;
+
+// Number of puzzles of the events from each year on: events were cut down
+// from 25 to 12 puzzles in 2025
+const PUZZLE_DAYS: [(EventYear, PuzzleDay); 2] = [(2015, 25), (2025, 12)];
+
+// Puzzle schedule of an event: one puzzle unlocks every day at midnight in
+// the release timezone, starting on December 1st
+#[derive(Clone, Copy, Debug)]
+pub struct EventCalendar {
+    year: EventYear,
+    num_days: PuzzleDay,
+}
+
+impl EventCalendar {
+    pub fn new(year: EventYear) -> Self {
+        let num_days = PUZZLE_DAYS
+            .iter()
+            .rev()
+            .find(|&&(from_year, _)| year >= from_year)
+            .map_or(PUZZLE_DAYS[0].1, |&(_, num_days)| num_days);
+        Self { year, num_days }
+    }
+
+    pub fn year(&self) -> EventYear {
+        self.year
+    }
+
+    pub fn num_days(&self) -> PuzzleDay {
+        self.num_days
+    }
+
+    pub fn days(&self) -> RangeInclusive<PuzzleDay> {
+        1..=self.num_days
+    }
+
+    pub fn has_day(&self, day: PuzzleDay) -> bool {
+        self.days().contains(&day)
+    }
+
+    pub fn unlock_time(&self, day: PuzzleDay) -> Option<DateTime<FixedOffset>> {
+        if !self.has_day(day) {
+            return None;
+        }
+        let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)?;
+        let puzzle_date = NaiveDate::from_ymd_opt(
+            self.year,
+            EVENT_START_MONTH,
+            EVENT_START_DAY + u32::from(day) - 1,
+        )?;
+        timezone
+            .from_local_datetime(&puzzle_date.and_hms_opt(0, 0, 0)?)
+            .single()
+    }
+
+    pub fn unlock_timestamp(&self, day: PuzzleDay) -> Option<Timestamp> {
+        self.unlock_time(day).map(|time| time.timestamp())
+    }
+
+    // Number of puzzles unlocked so far
+    pub fn last_unlock_day(&self, clock: &Clock) -> PuzzleDay {
+        let now = clock.now();
+        self.days()
+            .take_while(|&day| {
+                self.unlock_time(day).is_some_and(|time| time <= now)
+            })
+            .last()
+            .unwrap_or(0)
+    }
+
+    // An event is considered past a month after its last puzzle unlocked,
+    // when its leaderboards are not expected to change much anymore
+    pub fn is_past_event(&self, clock: &Clock) -> bool {
+        self.unlock_time(self.num_days)
+            .map(|last_unlock| {
+                clock.now() >= last_unlock + Duration::days(PAST_EVENT_DAYS)
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn frozen_at(time: &str) -> Clock {
+        Clock::Frozen(DateTime::parse_from_rfc3339(time).unwrap().into())
+    }
+
+    #[test]
+    fn num_days_changes_in_2025() {
+        assert_eq!(EventCalendar::new(2015).num_days(), 25);
+        assert_eq!(EventCalendar::new(2024).num_days(), 25);
+        assert_eq!(EventCalendar::new(2025).num_days(), 12);
+        assert_eq!(EventCalendar::new(2026).num_days(), 12);
+        assert!(EventCalendar::new(2024).has_day(25));
+        assert!(!EventCalendar::new(2025).has_day(13));
+        assert!(EventCalendar::new(2025).unlock_time(13).is_none());
+    }
+
+    #[test]
+    fn puzzles_unlock_at_midnight_release_time() {
+        let calendar = EventCalendar::new(2022);
+        let clock = frozen_at("2022-12-01T04:59:59Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 0);
+        let clock = frozen_at("2022-12-01T05:00:00Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 1);
+        let clock = frozen_at("2022-12-14T04:59:59Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 13);
+        let clock = frozen_at("2022-12-14T00:00:00-05:00");
+        assert_eq!(calendar.last_unlock_day(&clock), 14);
+        let clock = frozen_at("2023-07-01T00:00:00Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 25);
+    }
+
+    #[test]
+    fn last_unlock_day_stops_at_12_from_2025() {
+        let calendar = EventCalendar::new(2025);
+        let clock = frozen_at("2025-12-12T05:00:00Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 12);
+        let clock = frozen_at("2025-12-25T05:00:00Z");
+        assert_eq!(calendar.last_unlock_day(&clock), 12);
+    }
+
+    #[test]
+    fn events_are_past_a_month_after_last_unlock() {
+        let calendar = EventCalendar::new(2022);
+        assert!(!calendar.is_past_event(&frozen_at("2023-01-25T04:59:59Z")));
+        assert!(calendar.is_past_event(&frozen_at("2023-01-25T05:00:00Z")));
+
+        let calendar = EventCalendar::new(2025);
+        assert!(!calendar.is_past_event(&frozen_at("2026-01-12T04:59:59Z")));
+        assert!(calendar.is_past_event(&frozen_at("2026-01-12T05:00:00Z")));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::aoc::*;
+use crate::app::AppSettings;
+use crate::cache::{load_event, CacheKey};
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::iter::once;
+use std::path::Path;
+use std::time::SystemTime;
+
+// Validates the settings and tries to fetch every leaderboard, printing a
+// report along the way; returns whether everything is fine
+pub fn run_check(settings: &AppSettings, source: &MemberSource) -> bool {
+    let problems = validate_settings(settings);
+    println!("Settings:");
+    if problems.is_empty() {
+        println!("  OK");
+    }
+    for problem in problems.iter() {
+        println!("  {}", problem);
+    }
+
+    let uses_cookies = settings.fixtures_dir.is_none();
+    let mut failed_count = 0;
+    for (title, year, leaderboard_ids) in probed_lists(settings) {
+        println!("{}:", title);
+        let mut probed_ids = HashSet::new();
+        for leaderboard_id in leaderboard_ids {
+            if !probed_ids.insert(leaderboard_id) {
+                continue;
+            }
+            if !probe_leaderboard(
+                settings,
+                source,
+                year,
+                leaderboard_id,
+                uses_cookies,
+            ) {
+                failed_count += 1;
+            }
+        }
+    }
+
+    problems.is_empty() && failed_count == 0
+}
+
+// Each list of leaderboard IDs, along with a year to probe its leaderboards
+// with: the latest year that uses the main list, and the year of each of the
+// per-year lists
+fn probed_lists(settings: &AppSettings) -> Vec<(String, EventYear, &[String])> {
+    let latest_year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
+    let main_year = (FIRST_EVENT_YEAR..=latest_year)
+        .rev()
+        .find(|year| !settings.year_leaderboard_ids.contains_key(year));
+    let mut year_ids = settings
+        .year_leaderboard_ids
+        .iter()
+        .filter(|(&year, _)| {
+            Some(year) == settings.latest_event_year
+                || is_valid_event_year(year, &settings.clock)
+        })
+        .map(|(&year, ids)| {
+            (
+                format!("Leaderboards (years.{}, {} event)", year, year),
+                year,
+                ids.as_slice(),
+            )
+        })
+        .collect::<Vec<_>>();
+    year_ids.sort_unstable_by_key(|(_, year, _)| Reverse(*year));
+
+    main_year
+        .map(|year| {
+            (
+                format!("Leaderboards ({} event)", year),
+                year,
+                settings.leaderboard_ids.as_slice(),
+            )
+        })
+        .into_iter()
+        .chain(year_ids)
+        .collect()
+}
+
+// Tries to fetch a leaderboard and prints the outcome; returns whether it
+// could be fetched
+fn probe_leaderboard(
+    settings: &AppSettings,
+    source: &MemberSource,
+    year: EventYear,
+    leaderboard_id: &String,
+    uses_cookies: bool,
+) -> bool {
+    match source.get_leaderboard_members(year, leaderboard_id) {
+        Ok(members) if uses_cookies => println!(
+            "  {}: OK, {} members, session cookie valid as of {}",
+            leaderboard_id,
+            members.len(),
+            format_time(SystemTime::now())
+        ),
+        Ok(members) => {
+            println!("  {}: OK, {} members", leaderboard_id, members.len())
+        }
+        Err(err) => {
+            println!("  {}: FAILED, {}", leaderboard_id, err);
+            if uses_cookies {
+                let last_valid = settings
+                    .cache_dir
+                    .as_ref()
+                    .and_then(|cache_dir| {
+                        last_fetched_at(
+                            cache_dir,
+                            settings,
+                            source,
+                            leaderboard_id,
+                        )
+                    })
+                    .map(format_time)
+                    .unwrap_or_else(|| "unknown".to_string());
+                println!("    session cookie last valid: {}", last_valid);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_settings(settings: &AppSettings) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut year_ids = settings.year_leaderboard_ids.iter().collect::<Vec<_>>();
+    year_ids.sort_unstable_by_key(|(year, _)| **year);
+    for (year, _) in year_ids.iter() {
+        if Some(**year) != settings.latest_event_year
+            && !is_valid_event_year(**year, &settings.clock)
+        {
+            problems.push(format!("years.{} is not a valid event year", year));
+        }
+    }
+
+    let id_lists =
+        once(("leaderboard_ids".to_string(), &settings.leaderboard_ids)).chain(
+            year_ids.into_iter().map(|(year, ids)| {
+                (format!("years.{}.leaderboard_ids", year), ids)
+            }),
+        );
+    let mut all_ids = HashSet::new();
+    for (setting, leaderboard_ids) in id_lists {
+        let mut seen_ids = HashSet::new();
+        for leaderboard_id in leaderboard_ids.iter() {
+            if !seen_ids.insert(leaderboard_id) {
+                problems.push(format!(
+                    "leaderboard ID {} is repeated in {}",
+                    leaderboard_id, setting
+                ));
+            }
+            if !all_ids.insert(leaderboard_id) {
+                continue;
+            }
+            if leaderboard_id.parse::<u64>().is_err() {
+                problems.push(format!(
+                    "leaderboard ID {} is not a number",
+                    leaderboard_id
+                ));
+            }
+            if settings.fixtures_dir.is_none()
+                && settings.session_cookie(leaderboard_id).is_empty()
+            {
+                problems.push(format!(
+                    "leaderboard {} has no session cookie",
+                    leaderboard_id
+                ));
+            }
+        }
+    }
+
+    // Private leaderboards have the same ID as their owner, who is always
+    // one of their members
+    for member_id in settings.exclude_members.iter() {
+        if all_ids.contains(&member_id.to_string()) {
+            problems.push(format!(
+                "member {} is excluded but owns leaderboard {}",
+                member_id, member_id
+            ));
+        }
+    }
+
+    // Later years are fine, so that the next event can be previewed
+    if let Some(year) = settings.latest_event_year {
+        if year < FIRST_EVENT_YEAR {
+            problems.push(format!(
+                "latest_event_year {} is before the first event in {}",
+                year, FIRST_EVENT_YEAR
+            ));
+        }
+    }
+
+    problems
+}
+
+// Last time the leaderboard was fetched successfully, according to the cache
+fn last_fetched_at(
+    cache_dir: &Path,
+    settings: &AppSettings,
+    source: &MemberSource,
+    leaderboard_id: &String,
+) -> Option<SystemTime> {
+    let exclude_members = settings.exclude_members.iter().copied().collect();
+    (FIRST_EVENT_YEAR..=latest_event_year(&settings.clock))
+        .filter_map(|year| {
+            let key = CacheKey {
+                source,
+                leaderboard_ids: settings.event_leaderboard_ids(year),
+                exclude_members: &exclude_members,
+            };
+            load_event(cache_dir, year, &key).ok().flatten()
+        })
+        .filter(|cached| {
+            !cached.fetched.failed_leaderboards.contains(leaderboard_id)
+        })
+        .map(|cached| cached.updated_at)
+        .max()
+}
+
+fn format_time(time: SystemTime) -> String {
+    Into::<DateTime<Utc>>::into(time)
+        .format("%F %T %Z")
+        .to_string()
+}
@@ -0,0 +1,41 @@
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+
+const DEFAULT_SETTINGS_FILE: &str = "settings";
+
+/// Merge multiple Advent of Code leaderboards
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Settings file, with or without its extension
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        default_value = DEFAULT_SETTINGS_FILE
+    )]
+    pub config: String,
+
+    /// Address to listen on, overriding Rocket.toml
+    #[arg(short, long)]
+    pub address: Option<String>,
+
+    /// Port to listen on, overriding Rocket.toml
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Log level: off, error, warn, info, debug or trace (default: info,
+    /// unless set with RUST_LOG)
+    #[arg(short, long, value_name = "LEVEL")]
+    pub log_level: Option<LevelFilter>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Validate the settings and try to fetch every leaderboard, exiting with
+    /// a non-zero status if there are any problems
+    Check,
+}
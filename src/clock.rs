@@ -0,0 +1,56 @@
+use chrono::{DateTime, Duration, Utc};
+
+// Source of the current time for everything that depends on the event
+// calendar, which can be simulated to preview leaderboards off-season
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Clock {
+    #[default]
+    System,
+
+    // Runs from a simulated time on, offset from the system time
+    Offset(Duration),
+
+    // Stands still at a simulated time
+    Frozen(DateTime<Utc>),
+}
+
+impl Clock {
+    pub fn simulated(now: DateTime<Utc>, frozen: bool) -> Self {
+        if frozen {
+            Self::Frozen(now)
+        } else {
+            Self::Offset(now - Utc::now())
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Self::System => Utc::now(),
+            Self::Offset(offset) => Utc::now() + *offset,
+            Self::Frozen(now) => *now,
+        }
+    }
+
+    // Current time, only if it is simulated
+    pub fn simulated_now(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::System => None,
+            _ => Some(self.now()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulated_clock_runs_from_simulated_time() {
+        let now = DateTime::parse_from_rfc3339("2022-12-14T06:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(Clock::simulated(now, true).now(), now);
+        let running = Clock::simulated(now, false).now();
+        assert!(running >= now && running - now < Duration::seconds(60));
+    }
+}
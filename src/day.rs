@@ -0,0 +1,166 @@
+use crate::aoc::*;
+use crate::calendar::EventCalendar;
+use crate::error::*;
+use crate::leaderboard::{AsOf, EventNotices};
+use crate::leaders::*;
+use crate::util::*;
+use crate::AppSettings;
+use rocket::http::Status;
+use rocket_contrib::templates::Template;
+use serde::Serialize;
+use std::sync::Arc;
+
+const NOT_YET: &str = "-";
+
+pub fn render_day(
+    settings: &AppSettings,
+    event_mgr: Arc<EventManager>,
+    year: EventYear,
+    day: PuzzleDay,
+    as_of: Option<AsOf>,
+) -> Result<Template, ErrorPage> {
+    // Days that unlock later, or that had not unlocked yet as of the given
+    // time, have no timings to show
+    let calendar = EventCalendar::new(year);
+    let last_unlock_day =
+        calendar.last_unlock_day(&AsOf::clock(as_of, settings));
+    let Some(unlock_ts) = calendar
+        .unlock_timestamp(day)
+        .filter(|_| day <= last_unlock_day)
+    else {
+        return Err(render_error(
+            settings,
+            year,
+            Status::NotFound,
+            format!(
+                "There is no unlocked puzzle for day {} of Advent of Code {}.",
+                day, year
+            ),
+        ));
+    };
+
+    let as_of_ts = as_of.map(|AsOf(dt)| dt.timestamp());
+    let config = event_mgr.config();
+    let timings = match get_day_timings(event_mgr, year, day, as_of_ts) {
+        EventStatus::Ready(timings) => timings,
+        EventStatus::Refreshing => {
+            return Ok(render_refreshing(settings, year));
+        }
+        EventStatus::Failed(err) => {
+            return Err(render_fetch_error(settings, year, &err));
+        }
+    };
+    let update_sec = config.update_sec(year, timings.refresh_error().is_some());
+    let context = Context::build(
+        settings, &calendar, day, unlock_ts, as_of, timings, update_sec,
+    );
+    Ok(Template::render("day", context))
+}
+
+#[derive(Serialize)]
+struct Context<'a> {
+    aoc_base_url: &'a str,
+    year: EventYear,
+    day: PuzzleDay,
+    unlocked_days: Vec<PuzzleDay>,
+    #[serde(flatten)]
+    notices: EventNotices,
+    leaderboard_name: &'a str,
+    leaderboard_update_sec: u64,
+    table_head: String,
+    rows: Vec<String>,
+    member_names: Vec<String>,
+}
+
+impl<'a> Context<'a> {
+    fn build(
+        settings: &'a AppSettings,
+        calendar: &EventCalendar,
+        day: PuzzleDay,
+        unlock_ts: Timestamp,
+        as_of: Option<AsOf>,
+        timings: DayTimings,
+        update_sec: u64,
+    ) -> Self {
+        let year = calendar.year();
+        let notices = EventNotices::build(
+            settings,
+            as_of,
+            timings.updated_at(),
+            timings.failed_leaderboards(),
+            timings.refresh_error(),
+        );
+        let members = timings.get_members();
+
+        // Columns: rank, time to each star since unlock, and part 2 delta
+        let columns = members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| {
+                let since_unlock = |ts: Timestamp| {
+                    format_duration(
+                        usize::try_from(ts - unlock_ts).unwrap_or(0),
+                    )
+                };
+                [
+                    format!("{})", index + 1),
+                    since_unlock(member.part1_ts()),
+                    member
+                        .part2_ts()
+                        .map_or_else(|| NOT_YET.to_string(), since_unlock),
+                    member.part2_delta().map_or_else(
+                        || NOT_YET.to_string(),
+                        |delta| {
+                            format_duration(usize::try_from(delta).unwrap_or(0))
+                        },
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let headers = ["", "Part 1", "Part 2", "Delta"];
+        let widths = (0..headers.len())
+            .map(|col| {
+                columns
+                    .iter()
+                    .map(|row| row[col].len())
+                    .chain([headers[col].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        let format_row = |cells: &[&str]| {
+            cells
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+        let table_head = format_row(&headers);
+        let rows = columns
+            .iter()
+            .map(|row| {
+                format_row(&row.iter().map(String::as_str).collect::<Vec<_>>())
+            })
+            .collect();
+        let member_names = members
+            .iter()
+            .map(|member| member.get_name().clone())
+            .collect();
+        let last_unlock_day =
+            calendar.last_unlock_day(&AsOf::clock(as_of, settings));
+
+        Self {
+            aoc_base_url: &settings.aoc_base_url,
+            year,
+            day,
+            unlocked_days: (1..=last_unlock_day).collect(),
+            notices,
+            leaderboard_name: &settings.leaderboard_name,
+            leaderboard_update_sec: update_sec,
+            table_head,
+            rows,
+            member_names,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::aoc::*;
+use crate::AppSettings;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket_contrib::templates::Template;
+use serde::Serialize;
+
+pub type ErrorPage = Custom<Template>;
+
+pub fn render_error(
+    settings: &AppSettings,
+    year: EventYear,
+    status: Status,
+    message: String,
+) -> ErrorPage {
+    let context = Context {
+        aoc_base_url: &settings.aoc_base_url,
+        year,
+        status: status.code,
+        reason: status.reason,
+        message,
+    };
+    Custom(status, Template::render("error", context))
+}
+
+pub fn render_refreshing(settings: &AppSettings, year: EventYear) -> Template {
+    let context = Context {
+        aoc_base_url: &settings.aoc_base_url,
+        year,
+        status: Status::Ok.code,
+        reason: Status::Ok.reason,
+        message: "This leaderboard is being fetched from Advent of Code, \
+            please wait a moment."
+            .to_string(),
+    };
+    Template::render("refreshing", context)
+}
+
+pub fn render_fetch_error(
+    settings: &AppSettings,
+    year: EventYear,
+    err: &FetchError,
+) -> ErrorPage {
+    let (status, message) = match err {
+        FetchError::InvalidSessionCookie { .. } => (
+            Status::InternalServerError,
+            "This leaderboard is not configured correctly, so it cannot be \
+            fetched from Advent of Code."
+                .to_string(),
+        ),
+        FetchError::Network { .. } => (
+            Status::BadGateway,
+            "Advent of Code could not be reached. Please try again in a few \
+            minutes."
+                .to_string(),
+        ),
+        FetchError::InvalidLeaderboard {
+            year,
+            leaderboard_id,
+        } => (
+            Status::NotFound,
+            format!(
+                "Private leaderboard {} does not exist for the {} event.",
+                leaderboard_id, year
+            ),
+        ),
+        FetchError::Unauthorized { leaderboard_id } => (
+            Status::BadGateway,
+            format!(
+                "Advent of Code refused access to private leaderboard {}. \
+                The session cookie used to fetch it has probably expired or \
+                no longer has access to it, and needs to be renewed by the \
+                administrator of this site.",
+                leaderboard_id
+            ),
+        ),
+        FetchError::RateLimited { .. } => (
+            Status::ServiceUnavailable,
+            "Advent of Code is limiting the rate of requests from this site. \
+            Please try again in a few minutes."
+                .to_string(),
+        ),
+        FetchError::Upstream { status, .. } => (
+            Status::BadGateway,
+            format!(
+                "Advent of Code is having problems right now ({}). Please \
+                try again in a few minutes.",
+                status
+            ),
+        ),
+        FetchError::UnexpectedStatus { status, .. } => (
+            Status::BadGateway,
+            format!("Advent of Code sent an unexpected response ({}).", status),
+        ),
+        FetchError::MalformedResponse { leaderboard_id, .. } => (
+            Status::BadGateway,
+            format!(
+                "Private leaderboard {} could not be read because it is not \
+                in the expected format.",
+                leaderboard_id
+            ),
+        ),
+        FetchError::Fixture { .. } => (
+            Status::InternalServerError,
+            "A leaderboard file could not be loaded.".to_string(),
+        ),
+    };
+    render_error(settings, year, status, message)
+}
+
+#[derive(Serialize)]
+struct Context<'a> {
+    aoc_base_url: &'a str,
+    year: EventYear,
+    status: u16,
+    reason: &'a str,
+    message: String,
+}
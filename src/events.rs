@@ -5,16 +5,21 @@ use serde::Serialize;
 
 pub fn render_events(settings: &AppSettings, year: EventYear) -> Template {
     let latest_year = match settings.latest_event_year {
-        Some(y) => y.max(latest_event_year()),
-        _ => latest_event_year(),
+        Some(y) => y.max(latest_event_year(&settings.clock)),
+        _ => latest_event_year(&settings.clock),
     };
     let events = (FIRST_EVENT_YEAR..=latest_year).rev().collect::<Vec<_>>();
-    let context = Context { year, events };
+    let context = Context {
+        aoc_base_url: &settings.aoc_base_url,
+        year,
+        events,
+    };
     Template::render("events", &context)
 }
 
 #[derive(Serialize)]
-struct Context {
+struct Context<'a> {
+    aoc_base_url: &'a str,
     year: EventYear,
     events: Vec<EventYear>,
 }
@@ -1,17 +1,29 @@
 use crate::aoc::*;
+use crate::calendar::EventCalendar;
+use crate::clock::Clock;
+use crate::error::*;
 use crate::leaders::*;
+use crate::scoring::*;
 use crate::util::*;
 use crate::AppSettings;
 use chrono::{DateTime, FixedOffset, Utc};
-use log::error;
-use rocket::{http::RawStr, http::Status, request::FromFormValue};
+use rocket::{http::RawStr, request::FromFormValue};
 use rocket_contrib::templates::Template;
 use serde::Serialize;
 use std::convert::TryFrom;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 #[derive(Clone, Copy)]
-pub struct AsOf(DateTime<FixedOffset>);
+pub struct AsOf(pub DateTime<FixedOffset>);
+
+impl AsOf {
+    // Clock to tell which puzzles had unlocked as of the given time, or by
+    // now if none is given
+    pub fn clock(as_of: Option<Self>, settings: &AppSettings) -> Clock {
+        as_of.map_or(settings.clock, |AsOf(dt)| Clock::Frozen(dt.into()))
+    }
+}
 
 impl<'v> FromFormValue<'v> for AsOf {
     type Error = &'v RawStr;
@@ -34,41 +46,103 @@ impl<'v> FromFormValue<'v> for LeaderboardOrder {
 
 pub fn render_leaderboard(
     settings: &AppSettings,
-    event_mgr: Arc<RwLock<EventManager>>,
+    event_mgr: Arc<EventManager>,
     year: EventYear,
     leaderboard_order: Option<LeaderboardOrder>,
     as_of: Option<AsOf>,
-) -> Result<Template, Status> {
+) -> Result<Template, ErrorPage> {
     let order = leaderboard_order.unwrap_or(settings.leaderboard_default_order);
-    let leaderboard = get_leaderboard(
-        event_mgr,
-        year,
-        order,
-        as_of.map(|AsOf(dt)| dt.timestamp()),
-    )
-    .map_err(|err| {
-        error!("Failed to fetch {} event: {}", year, err);
-        // TODO: customize 500 page
-        Status::InternalServerError
-    })?;
-    let context = Context::build(settings, year, as_of, leaderboard, order);
+    let as_of_ts = as_of.map(|AsOf(dt)| dt.timestamp());
+    let config = event_mgr.config();
+    let leaderboard = match get_leaderboard(event_mgr, year, order, as_of_ts) {
+        LeaderboardStatus::Ready(leaderboard) => leaderboard,
+        LeaderboardStatus::Refreshing => {
+            return Ok(render_refreshing(settings, year));
+        }
+        LeaderboardStatus::Failed(err) => {
+            return Err(render_fetch_error(settings, year, &err));
+        }
+    };
+    let update_sec =
+        config.update_sec(year, leaderboard.refresh_error().is_some());
+    let context =
+        Context::build(settings, year, as_of, leaderboard, order, update_sec);
     Ok(Template::render("leaderboard", context))
 }
 
+// Notices about how current the data of an event is, shared by all pages
+// showing an event (see the notices template)
+#[derive(Serialize)]
+pub struct EventNotices {
+    as_of_str: Option<String>,
+    updated_at_str: String,
+    failed_leaderboards: Vec<String>,
+    refresh_error: Option<String>,
+    stale_minutes: u64,
+}
+
+impl EventNotices {
+    pub fn build(
+        settings: &AppSettings,
+        as_of: Option<AsOf>,
+        updated_at: SystemTime,
+        failed_leaderboards: &[String],
+        refresh_error: Option<&str>,
+    ) -> Self {
+        let as_of_str = as_of.map(|AsOf(dt)| dt.to_string()).or_else(|| {
+            settings.clock.simulated_now().map(|now| now.to_string())
+        });
+        let updated_at_str = Into::<DateTime<Utc>>::into(updated_at)
+            .format("%F %T %Z")
+            .to_string();
+        let stale_minutes =
+            updated_at.elapsed().map_or(0, |dur| dur.as_secs() / 60);
+        Self {
+            as_of_str,
+            updated_at_str,
+            failed_leaderboards: failed_leaderboards.to_vec(),
+            refresh_error: refresh_error.map(String::from),
+            stale_minutes,
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct Context<'a> {
+    aoc_base_url: &'a str,
     year: EventYear,
-    as_of_str: Option<String>,
+    #[serde(flatten)]
+    notices: EventNotices,
     leaderboard_name: &'a str,
     members: Vec<ScoredMember>,
     leaderboard_order: LeaderboardOrder,
     leaderboard_default_order: &'a LeaderboardOrder,
+    orderings: Vec<StrategyInfo>,
+    score_suffix: &'static str,
     leaderboard_update_sec: u64,
-    updated_at_str: String,
     rank_str: Vec<String>,
     score_str: Vec<String>,
     table_head_pad: String,
-    last_unlock_day: i64,
+    days: Vec<DayHeader>,
+    last_unlock_day: PuzzleDay,
+}
+
+// Day numbers above 9 are written in two rows, tens above units
+#[derive(Serialize)]
+struct DayHeader {
+    day: PuzzleDay,
+    tens: Option<PuzzleDay>,
+    units: PuzzleDay,
+}
+
+impl DayHeader {
+    fn new(day: PuzzleDay) -> Self {
+        Self {
+            day,
+            tens: Some(day / 10).filter(|&tens| tens > 0),
+            units: day % 10,
+        }
+    }
 }
 
 impl<'a> Context<'a> {
@@ -78,40 +152,52 @@ impl<'a> Context<'a> {
         as_of: Option<AsOf>,
         leaderboard: Leaderboard,
         leaderboard_order: LeaderboardOrder,
+        update_sec: u64,
     ) -> Self {
-        let as_of_str = as_of.map(|AsOf(dt)| dt.to_string());
-        let updated_at_str =
-            Into::<DateTime<Utc>>::into(leaderboard.updated_at())
-                .format("%F %T %Z")
-                .to_string();
+        let notices = EventNotices::build(
+            settings,
+            as_of,
+            leaderboard.updated_at(),
+            leaderboard.failed_leaderboards(),
+            leaderboard.refresh_error(),
+        );
         let members = leaderboard.get_members();
         let rank_width = number_width(members.len());
         let rank_str = (1..=members.len())
             .map(|rank| format!("{:width$}", rank, width = rank_width))
             .collect::<Vec<_>>();
-        let score_width = number_width(
-            members.get(0).map(|member| member.get_score()).unwrap_or(0),
-        );
-        let score_str = members
+        let strategy = leaderboard_order.strategy();
+        let scores = members
             .iter()
-            .map(|m| format!("{:width$}", m.get_score(), width = score_width))
+            .map(|member| strategy.format_score(member.get_score()))
+            .collect::<Vec<_>>();
+        let score_width = scores.iter().map(String::len).max().unwrap_or(1);
+        let score_str = scores
+            .iter()
+            .map(|score| format!("{:>width$}", score, width = score_width))
             .collect::<Vec<_>>();
         let table_head_pad =
             vec![' '; rank_width + score_width].into_iter().collect();
-        let last_unlock_day = last_unlock_day(year);
+        let calendar = EventCalendar::new(year);
+        let days = calendar.days().map(DayHeader::new).collect();
+        let last_unlock_day =
+            calendar.last_unlock_day(&AsOf::clock(as_of, settings));
 
         Self {
+            aoc_base_url: &settings.aoc_base_url,
             year,
-            as_of_str,
+            notices,
             leaderboard_name: &settings.leaderboard_name,
             members,
             leaderboard_order,
             leaderboard_default_order: &settings.leaderboard_default_order,
-            leaderboard_update_sec: settings.leaderboard_update_sec,
-            updated_at_str,
+            orderings: strategy_infos(),
+            score_suffix: strategy.score_suffix(),
+            leaderboard_update_sec: update_sec,
             rank_str,
             score_str,
             table_head_pad,
+            days,
             last_unlock_day,
         }
     }
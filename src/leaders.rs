@@ -1,159 +1,455 @@
 use crate::aoc::*;
-use log::debug;
-use serde::{Deserialize, Serialize};
-use std::cmp::{Ordering, Reverse};
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use crate::cache::*;
+use crate::calendar::EventCalendar;
+use crate::clock::Clock;
+use crate::scheduler::*;
+use crate::scoring::*;
+use log::{debug, error, info, warn};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
-use std::error::Error;
-use std::sync::{Arc, RwLock};
-use std::time::SystemTime;
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 pub struct EventManager {
-    leaderboard_ids: Vec<String>,
-    session_cookie: String,
-    update_sec: u64,
-    exclude_members: HashSet<MemberId>,
-    events: HashMap<EventYear, Event>,
+    config: RwLock<Arc<EventConfig>>,
+    cache_dir: Option<PathBuf>,
+    entries: RwLock<HashMap<EventYear, Arc<EventEntry>>>,
+    refresh_trigger: Arc<RefreshTrigger>,
+}
+
+// Settings that determine how events are fetched and how often, which may be
+// replaced while the app is running
+pub struct EventConfig {
+    pub source: MemberSource,
+    pub leaderboard_ids: Vec<String>,
+    pub year_leaderboard_ids: HashMap<EventYear, Vec<String>>,
+    pub update_sec: u64,
+    pub past_event_update_sec: u64,
+    pub exclude_members: HashSet<MemberId>,
+    pub clock: Clock,
+    pub scoring: ScoringConfig,
+}
+
+impl EventConfig {
+    pub fn event_leaderboard_ids(&self, year: EventYear) -> &[String] {
+        self.year_leaderboard_ids
+            .get(&year)
+            .unwrap_or(&self.leaderboard_ids)
+    }
+
+    // Past events rarely change, so they are refreshed much less often, unless
+    // the last refresh failed
+    pub fn update_sec(&self, year: EventYear, failed: bool) -> u64 {
+        if !failed && EventCalendar::new(year).is_past_event(&self.clock) {
+            self.past_event_update_sec
+        } else {
+            self.update_sec
+        }
+    }
+}
+
+// Each year has its own lock, so that refreshing one event doesn't block
+// readers of the others, and its own refresh lock, so that concurrent
+// refreshes of the same event share a single fetch
+#[derive(Default)]
+struct EventEntry {
+    state: RwLock<EventState>,
+    refresh: Mutex<()>,
+}
+
+// The generation is bumped whenever the event is invalidated, so that fetches
+// started before that are discarded; readmitted members are only found by
+// fetching the same leaderboards again, which therefore skips the minimum
+// fetch interval
+#[derive(Default)]
+struct EventState {
+    event: Option<Event>,
+    error: Option<Arc<FetchError>>,
+    refreshed_at: Option<SystemTime>,
+    generation: u64,
+    readmitted: bool,
 }
 
 impl EventManager {
-    pub fn new(
-        leaderboard_ids: Vec<String>,
-        session_cookie: String,
-        update_sec: u64,
-        exclude_members: HashSet<MemberId>,
-    ) -> Self {
+    pub fn new(config: EventConfig, cache_dir: Option<PathBuf>) -> Self {
         Self {
-            leaderboard_ids,
-            session_cookie,
-            update_sec,
-            events: HashMap::new(),
-            exclude_members,
+            config: RwLock::new(Arc::new(config)),
+            cache_dir,
+            entries: RwLock::new(HashMap::new()),
+            refresh_trigger: Arc::new(RefreshTrigger::default()),
         }
     }
 
-    fn get_event(&self, year: EventYear) -> Option<&Event> {
-        self.events.get(&year).filter(|&event| {
-            event.updated_at.elapsed().map_or(0, |dur| dur.as_secs())
-                < self.update_sec
-        })
+    pub fn config(&self) -> Arc<EventConfig> {
+        // TODO: handle LockResult errors
+        self.config.read().unwrap().clone()
     }
 
-    fn update_event(&mut self, year: EventYear) -> Result<(), Box<dyn Error>> {
-        if self.get_event(year).is_some() {
-            debug!("{} event is already up to date", year);
-            return Ok(());
+    // Replace the configuration and invalidate the events it affects: events
+    // are fetched again if their leaderboards or their session cookies changed
+    // or if members are no longer excluded, whereas newly excluded members are
+    // simply removed
+    pub fn reconfigure(&self, config: EventConfig) {
+        // Swap the configuration in before invalidating any event, so that
+        // refreshes that see a new generation also see the new configuration
+        let config = Arc::new(config);
+        // TODO: handle LockResult errors
+        let old_config =
+            mem::replace(&mut *self.config.write().unwrap(), config.clone());
+        let ids_changed = |year| {
+            old_config
+                .event_leaderboard_ids(year)
+                .iter()
+                .collect::<HashSet<_>>()
+                != config
+                    .event_leaderboard_ids(year)
+                    .iter()
+                    .collect::<HashSet<_>>()
+        };
+        // e.g. an expired cookie was renewed
+        let cookies_changed = |year| {
+            config.event_leaderboard_ids(year).iter().any(|id| {
+                old_config.source.session_cookie(id)
+                    != config.source.session_cookie(id)
+            })
+        };
+        let readmitted = !old_config
+            .exclude_members
+            .is_subset(&config.exclude_members);
+        let newly_excluded = config
+            .exclude_members
+            .difference(&old_config.exclude_members)
+            .copied()
+            .collect::<HashSet<_>>();
+        let mut invalidated_years = Vec::new();
+        // TODO: handle LockResult errors
+        for (&year, entry) in self.entries.read().unwrap().iter() {
+            let mut state = entry.state.write().unwrap();
+            if let Some(event) = &mut state.event {
+                event
+                    .members
+                    .retain(|m| !newly_excluded.contains(&m.get_id()));
+            }
+            if ids_changed(year) || cookies_changed(year) || readmitted {
+                state.refreshed_at = None;
+                state.generation += 1;
+                state.readmitted |= readmitted;
+                invalidated_years.push(year);
+            }
+        }
+        if !invalidated_years.is_empty() {
+            info!("Invalidated events: {:?}", invalidated_years);
         }
+        self.refresh_trigger.wake();
+    }
+
+    pub fn load_cache(&self, years: RangeInclusive<EventYear>) {
+        let Some(cache_dir) = &self.cache_dir else {
+            return;
+        };
+
+        let config = self.config();
+        for year in years {
+            let key = CacheKey {
+                source: &config.source,
+                leaderboard_ids: config.event_leaderboard_ids(year),
+                exclude_members: &config.exclude_members,
+            };
+            match load_event(cache_dir, year, &key) {
+                Ok(Some(mut cached)) => {
+                    cached.fetched.members.retain(|m| {
+                        !config.exclude_members.contains(&m.get_id())
+                    });
+                    info!(
+                        "Loaded {} event from cache with {} members",
+                        year,
+                        cached.fetched.members.len()
+                    );
+                    let entry = self.entry(year);
+                    // TODO: handle LockResult errors
+                    let mut state = entry.state.write().unwrap();
+                    state.refreshed_at = Some(cached.updated_at);
+                    state.event = Some(Event::new(
+                        year,
+                        cached.fetched,
+                        cached.updated_at,
+                    ));
+                }
+                Ok(None) => debug!("No cached {} event", year),
+                Err(err) => {
+                    warn!("Failed to load {} event from cache: {}", year, err)
+                }
+            }
+        }
+    }
+
+    pub fn request_refresh(&self, years: &[EventYear]) {
+        self.refresh_trigger.request_all(years.iter().copied());
+    }
 
-        debug!("Updating {} event", year);
+    pub fn refresh_trigger(&self) -> Arc<RefreshTrigger> {
+        self.refresh_trigger.clone()
+    }
+
+    fn entry(&self, year: EventYear) -> Arc<EventEntry> {
+        // TODO: handle LockResult errors
+        if let Some(entry) = self.entries.read().unwrap().get(&year) {
+            return entry.clone();
+        }
+        self.entries
+            .write()
+            .unwrap()
+            .entry(year)
+            .or_default()
+            .clone()
+    }
+
+    // Active years that were never fetched or whose events have expired
+    pub fn due_years(&self) -> Vec<EventYear> {
+        self.refresh_trigger
+            .active_years()
+            .into_iter()
+            .filter(|year| self.refresh_due_in(*year).is_zero())
+            .collect()
+    }
+
+    pub fn next_refresh_in(&self) -> Duration {
+        self.refresh_trigger
+            .active_years()
+            .into_iter()
+            .map(|year| self.refresh_due_in(year))
+            .min()
+            .unwrap_or_else(|| Duration::from_secs(self.config().update_sec))
+    }
+
+    fn refresh_due_in(&self, year: EventYear) -> Duration {
+        let config = self.config();
+        let entry = self.entry(year);
+        // TODO: handle LockResult errors
+        let state = entry.state.read().unwrap();
+        let update_sec = config.update_sec(year, state.error.is_some());
+        let update_due_in = state
+            .refreshed_at
+            .map(|refreshed_at| {
+                Duration::from_secs(update_sec).saturating_sub(
+                    refreshed_at.elapsed().unwrap_or(Duration::ZERO),
+                )
+            })
+            .unwrap_or(Duration::ZERO);
+        // Events whose leaderboards were fetched too recently, e.g. right
+        // before the event was invalidated, wait for the throttle rather than
+        // keeping a worker busy
+        let throttled_for = config
+            .source
+            .not_before(year, config.event_leaderboard_ids(year))
+            .filter(|_| !state.readmitted)
+            .map_or(Duration::ZERO, |not_before| {
+                not_before.saturating_duration_since(Instant::now())
+            });
+        update_due_in.max(throttled_for)
+    }
+
+    pub fn refresh_event(&self, year: EventYear) {
+        let entry = self.entry(year);
+
+        // TODO: handle LockResult errors
+        debug!("Attempting to acquire refresh lock on {} event", year);
+        let _refresh_guard = entry.refresh.lock().unwrap();
+        if !self.refresh_due_in(year).is_zero() {
+            debug!("{} event was refreshed in the meantime", year);
+            return;
+        }
+
+        // Fetch without holding the event lock, so that pages can still be
+        // served from the current event in the meantime
+        debug!("Refreshing {} event", year);
+        // TODO: handle LockResult errors
+        let generation = entry.state.read().unwrap().generation;
+        let result = self.fetch_event(year, &self.config());
+
+        // TODO: handle LockResult errors
+        let mut state = entry.state.write().unwrap();
+        if state.generation != generation {
+            debug!("{} event was invalidated while being refreshed", year);
+            return;
+        }
+        state.refreshed_at = Some(SystemTime::now());
+        state.readmitted = false;
+        match result {
+            Ok(event) => {
+                state.error = None;
+                state.event = Some(event);
+            }
+            Err(err) => {
+                error!("Failed to refresh {} event: {}", year, err);
+                state.error = Some(Arc::new(err));
+            }
+        }
+    }
+
+    fn fetch_event(
+        &self,
+        year: EventYear,
+        config: &EventConfig,
+    ) -> Result<Event, FetchError> {
         let updated_at = SystemTime::now();
-        // TODO: handle 404 response for invalid leaderboard ID or year
-        let members = fetch_members(
+        let fetched = config.source.get_members(
             year,
-            &self.leaderboard_ids,
-            &self.exclude_members,
-            &self.session_cookie,
+            config.event_leaderboard_ids(year),
+            &config.exclude_members,
         )?;
 
-        self.events.insert(year, Event::new(members, updated_at));
-        Ok(())
+        let event = Event::new(year, fetched, updated_at);
+        if let Some(cache_dir) = &self.cache_dir {
+            save_event(
+                cache_dir,
+                year,
+                &CacheKey {
+                    source: &config.source,
+                    leaderboard_ids: config.event_leaderboard_ids(year),
+                    exclude_members: &config.exclude_members,
+                },
+                &event.members,
+                &event.failed_leaderboards,
+                event.updated_at,
+            )
+            .unwrap_or_else(|err| {
+                warn!("Failed to save {} event to cache: {}", year, err)
+            });
+        }
+        Ok(event)
     }
 }
 
-struct Event {
+pub struct Event {
+    calendar: EventCalendar,
     members: HashSet<Member>,
+    failed_leaderboards: Vec<String>,
     updated_at: SystemTime,
 }
 
-#[derive(Clone, Copy, Deserialize, Serialize)]
-pub enum LeaderboardOrder {
-    #[serde(rename = "local_score")]
-    LocalScore,
+// One of the registered scoring strategies, referred to by its name
+#[derive(Clone, Copy)]
+pub struct LeaderboardOrder(&'static dyn ScoringStrategy);
 
-    #[serde(rename = "stars")]
-    Stars,
+impl LeaderboardOrder {
+    pub fn strategy(&self) -> &'static dyn ScoringStrategy {
+        self.0
+    }
 }
 
 impl TryFrom<&str> for LeaderboardOrder {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "local_score" => Ok(Self::LocalScore),
-            "stars" => Ok(Self::Stars),
-            _ => Err("Invalid leaderboard order"),
-        }
+        find_strategy(value)
+            .map(Self)
+            .ok_or("Invalid leaderboard order")
+    }
+}
+
+impl Serialize for LeaderboardOrder {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for LeaderboardOrder {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::try_from(name.as_str()).map_err(de::Error::custom)
     }
 }
 
 impl Event {
-    fn new(members: HashSet<Member>, updated_at: SystemTime) -> Self {
+    pub fn new(
+        year: EventYear,
+        fetched: FetchedMembers,
+        updated_at: SystemTime,
+    ) -> Self {
         Self {
-            members,
+            calendar: EventCalendar::new(year),
+            members: fetched.members,
+            failed_leaderboards: fetched.failed_leaderboards,
             updated_at,
         }
     }
 
+    pub fn members(&self) -> &HashSet<Member> {
+        &self.members
+    }
+
+    pub fn calendar(&self) -> &EventCalendar {
+        &self.calendar
+    }
+
     fn build_leaderboard(
         &self,
         order: LeaderboardOrder,
         as_of: Option<Timestamp>,
+        config: &EventConfig,
+        refresh_error: Option<String>,
     ) -> Leaderboard {
-        let mut scored_members = match order {
-            LeaderboardOrder::LocalScore => self.local_score(as_of),
-            LeaderboardOrder::Stars => self.star_score(as_of),
-        };
-        scored_members.sort_unstable();
-        scored_members.reverse();
-        Leaderboard::new(self.updated_at, scored_members)
-    }
-
-    fn local_score(&self, as_of: Option<Timestamp>) -> Vec<ScoredMember> {
-        let mut puzzles = HashMap::new();
-        for member in self.members.iter() {
-            for (puzzle_id, ts) in member.iter_completed() {
-                if as_of.map(|timestamp| *ts <= timestamp).unwrap_or(true) {
-                    puzzles
-                        .entry(*puzzle_id)
-                        .or_insert_with(BinaryHeap::new)
-                        .push(Reverse((ts, member)));
-                }
-            }
-        }
-
-        let mut scores = HashMap::new();
-        let max_points = self.members.len();
-        for (_, mut solutions) in puzzles.drain() {
-            let mut puzzle_points = max_points;
-            while let Some(Reverse((_, member))) = solutions.pop() {
-                *scores.entry(member).or_insert(0) += puzzle_points;
-                puzzle_points -= 1;
-            }
-        }
-
-        self.members
-            .iter()
-            .map(|member| {
-                ScoredMember::build(
-                    member,
-                    as_of,
-                    *scores.get(member).unwrap_or(&0),
-                )
-            })
-            .collect::<Vec<_>>()
+        let strategy = order.strategy();
+        let mut scored_members = strategy.score(self, as_of, config);
+        let lower_is_better = strategy.lower_is_better();
+        scored_members.sort_unstable_by(|a, b| a.rank_cmp(b, lower_is_better));
+        Leaderboard::new(
+            self.updated_at,
+            self.failed_leaderboards.clone(),
+            refresh_error,
+            scored_members,
+        )
     }
 
-    fn star_score(&self, as_of: Option<Timestamp>) -> Vec<ScoredMember> {
-        self.members
+    fn build_day_timings(
+        &self,
+        day: PuzzleDay,
+        as_of: Option<Timestamp>,
+        refresh_error: Option<String>,
+    ) -> DayTimings {
+        let mut members = self
+            .members
             .iter()
-            .map(|member| {
-                ScoredMember::build(member, as_of, member.star_count(as_of))
+            .filter_map(|member| {
+                Some(DayTiming {
+                    id: member.get_id(),
+                    name: member.get_name().clone(),
+                    part1_ts: member.get_star_ts((day, 1), as_of)?,
+                    part2_ts: member.get_star_ts((day, 2), as_of),
+                })
             })
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+        // Quickest to get the second star first, followed by those who only
+        // got the first one
+        members.sort_unstable_by_key(|member| {
+            (
+                member.part2_delta().is_none(),
+                member.part2_delta(),
+                member.part1_ts,
+                member.id,
+            )
+        });
+        DayTimings {
+            updated_at: self.updated_at,
+            failed_leaderboards: self.failed_leaderboards.clone(),
+            refresh_error,
+            members,
+        }
     }
 }
 
-#[derive(Eq, Serialize)]
+#[derive(Serialize)]
 pub struct ScoredMember {
     id: MemberId,
     name: String,
@@ -163,11 +459,16 @@ pub struct ScoredMember {
 }
 
 impl ScoredMember {
-    fn build(member: &Member, as_of: Option<Timestamp>, score: Score) -> Self {
+    pub fn build(
+        member: &Member,
+        calendar: &EventCalendar,
+        as_of: Option<Timestamp>,
+        score: Score,
+    ) -> Self {
         Self {
             id: member.get_id(),
             name: member.get_name().clone(),
-            stars: member.get_stars(as_of),
+            stars: member.get_stars(calendar, as_of),
             last_star: member.get_last_star(as_of),
             score,
         }
@@ -188,39 +489,42 @@ impl ScoredMember {
     pub fn get_score(&self) -> Score {
         self.score
     }
-}
 
-impl Ord for ScoredMember {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.score
-            .cmp(&other.score)
-            .then(other.last_star.cmp(&self.last_star))
-            .then(other.id.cmp(&self.id))
-    }
-}
-
-impl PartialOrd for ScoredMember {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for ScoredMember {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+    // Best score first, ties going to whoever got their last star first, and
+    // members without a score last
+    fn rank_cmp(&self, other: &Self, lower_is_better: bool) -> Ordering {
+        let by_score = if lower_is_better {
+            self.score.cmp(&other.score)
+        } else {
+            other.score.cmp(&self.score)
+        };
+        (self.score == NO_SCORE)
+            .cmp(&(other.score == NO_SCORE))
+            .then(by_score)
+            .then(self.last_star.cmp(&other.last_star))
+            .then(self.id.cmp(&other.id))
     }
 }
 
 #[derive(Serialize)]
 pub struct Leaderboard {
     updated_at: SystemTime,
+    failed_leaderboards: Vec<String>,
+    refresh_error: Option<String>,
     members: Vec<ScoredMember>,
 }
 
 impl Leaderboard {
-    fn new(updated_at: SystemTime, members: Vec<ScoredMember>) -> Self {
+    fn new(
+        updated_at: SystemTime,
+        failed_leaderboards: Vec<String>,
+        refresh_error: Option<String>,
+        members: Vec<ScoredMember>,
+    ) -> Self {
         Self {
             updated_at,
+            failed_leaderboards,
+            refresh_error,
             members,
         }
     }
@@ -229,30 +533,247 @@ impl Leaderboard {
         self.updated_at
     }
 
+    pub fn failed_leaderboards(&self) -> &[String] {
+        &self.failed_leaderboards
+    }
+
+    // Error of the last refresh attempt, if it failed after this leaderboard
+    // was fetched, in which case the leaderboard is stale
+    pub fn refresh_error(&self) -> Option<&str> {
+        self.refresh_error.as_deref()
+    }
+
     pub fn get_members(self) -> Vec<ScoredMember> {
         self.members
     }
 }
 
+// Day-level view of an event: when each member got the stars of a day
+pub struct DayTimings {
+    updated_at: SystemTime,
+    failed_leaderboards: Vec<String>,
+    refresh_error: Option<String>,
+    members: Vec<DayTiming>,
+}
+
+impl DayTimings {
+    pub fn updated_at(&self) -> SystemTime {
+        self.updated_at
+    }
+
+    pub fn failed_leaderboards(&self) -> &[String] {
+        &self.failed_leaderboards
+    }
+
+    pub fn refresh_error(&self) -> Option<&str> {
+        self.refresh_error.as_deref()
+    }
+
+    pub fn get_members(self) -> Vec<DayTiming> {
+        self.members
+    }
+}
+
+pub struct DayTiming {
+    id: MemberId,
+    name: String,
+    part1_ts: Timestamp,
+    part2_ts: Option<Timestamp>,
+}
+
+impl DayTiming {
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn part1_ts(&self) -> Timestamp {
+        self.part1_ts
+    }
+
+    pub fn part2_ts(&self) -> Option<Timestamp> {
+        self.part2_ts
+    }
+
+    pub fn part2_delta(&self) -> Option<Timestamp> {
+        self.part2_ts.map(|part2_ts| part2_ts - self.part1_ts)
+    }
+}
+
+pub enum EventStatus<T> {
+    Ready(T),
+    Refreshing,
+    Failed(Arc<FetchError>),
+}
+
+pub type LeaderboardStatus = EventStatus<Leaderboard>;
+pub type DayTimingsStatus = EventStatus<DayTimings>;
+
 pub fn get_leaderboard(
-    event_mgr: Arc<RwLock<EventManager>>,
+    event_mgr: Arc<EventManager>,
     year: EventYear,
     leaderboard_order: LeaderboardOrder,
     as_of: Option<Timestamp>,
-) -> Result<Leaderboard, Box<dyn Error>> {
-    loop {
-        // TODO: handle LockResult errors
-        debug!("Attempting to acquire read lock on {} event", year);
-        if let Some(event) = event_mgr.read().unwrap().get_event(year) {
-            debug!("Building leaderboard for {} event", year);
-            return Ok(event.build_leaderboard(leaderboard_order, as_of));
+) -> LeaderboardStatus {
+    let config = event_mgr.config();
+    read_event(&event_mgr, year, as_of, |event, as_of, refresh_error| {
+        debug!("Building leaderboard for {} event", year);
+        event.build_leaderboard(
+            leaderboard_order,
+            as_of,
+            &config,
+            refresh_error,
+        )
+    })
+}
+
+pub fn get_day_timings(
+    event_mgr: Arc<EventManager>,
+    year: EventYear,
+    day: PuzzleDay,
+    as_of: Option<Timestamp>,
+) -> DayTimingsStatus {
+    read_event(&event_mgr, year, as_of, |event, as_of, refresh_error| {
+        debug!("Building day {} timings for {} event", day, year);
+        event.build_day_timings(day, as_of, refresh_error)
+    })
+}
+
+fn read_event<T>(
+    event_mgr: &EventManager,
+    year: EventYear,
+    as_of: Option<Timestamp>,
+    build: impl FnOnce(&Event, Option<Timestamp>, Option<String>) -> T,
+) -> EventStatus<T> {
+    event_mgr.refresh_trigger.request(year);
+    let entry = event_mgr.entry(year);
+    // Stars acquired after a simulated time are not shown either
+    let as_of = as_of.or_else(|| {
+        event_mgr
+            .config()
+            .clock
+            .simulated_now()
+            .map(|now| now.timestamp())
+    });
+
+    // TODO: handle LockResult errors
+    debug!("Attempting to acquire read lock on {} event", year);
+    let state = entry.state.read().unwrap();
+    match (&state.event, &state.error) {
+        (Some(event), refresh_error) => EventStatus::Ready(build(
+            event,
+            as_of,
+            refresh_error.as_ref().map(|err| err.to_string()),
+        )),
+        (None, Some(err)) => EventStatus::Failed(err.clone()),
+        (None, None) => EventStatus::Refreshing,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::aoc::tests::member;
+    use chrono::DateTime;
+
+    pub fn frozen_at(time: &str) -> Clock {
+        Clock::Frozen(DateTime::parse_from_rfc3339(time).unwrap().into())
+    }
+
+    // Penalties of 1000 seconds per unsolved star are easy to tell apart from
+    // the times in tests
+    pub fn config_with_clock(clock: Clock) -> EventConfig {
+        EventConfig {
+            source: MemberSource::Fixtures(PathBuf::from("fixtures")),
+            leaderboard_ids: vec!["1".to_string()],
+            year_leaderboard_ids: HashMap::new(),
+            update_sec: 900,
+            past_event_update_sec: 86400,
+            exclude_members: HashSet::new(),
+            clock,
+            scoring: ScoringConfig {
+                unsolved_star_penalty_sec: 1000,
+            },
         }
+    }
 
-        // TODO: handle LockResult errors
-        debug!(
-            "{} event needs to be updated, attempting to acquire write lock",
-            year
+    pub fn event(year: EventYear, members: Vec<Member>) -> Event {
+        let fetched = FetchedMembers {
+            members: members.into_iter().collect(),
+            failed_leaderboards: Vec::new(),
+        };
+        Event::new(year, fetched, SystemTime::now())
+    }
+
+    fn event_mgr_with_clock(clock: Clock) -> EventManager {
+        let event_mgr = EventManager::new(config_with_clock(clock), None);
+        event_mgr.entry(2022).state.write().unwrap().event =
+            Some(event(2022, Vec::new()));
+        event_mgr
+    }
+
+    fn read_as_of(
+        event_mgr: &EventManager,
+        as_of: Option<Timestamp>,
+    ) -> Option<Timestamp> {
+        match read_event(event_mgr, 2022, as_of, |_, as_of, _| as_of) {
+            EventStatus::Ready(as_of) => as_of,
+            _ => panic!("2022 event not ready"),
+        }
+    }
+
+    #[test]
+    fn read_event_applies_simulated_now_as_of() {
+        let clock = frozen_at("2022-12-14T06:00:00Z");
+        let now_ts = clock.now().timestamp();
+        let event_mgr = event_mgr_with_clock(clock);
+        assert_eq!(read_as_of(&event_mgr, None), Some(now_ts));
+        assert_eq!(
+            read_as_of(&event_mgr, Some(now_ts - 60)),
+            Some(now_ts - 60)
         );
-        event_mgr.write().unwrap().update_event(year)?;
+
+        let event_mgr = event_mgr_with_clock(Clock::System);
+        assert_eq!(read_as_of(&event_mgr, None), None);
+    }
+
+    #[test]
+    fn leaderboards_rank_best_score_first_then_earliest_last_star() {
+        // Only day 1 has unlocked, at 2022-12-01T05:00:00Z
+        let config = config_with_clock(frozen_at("2022-12-01T12:00:00Z"));
+        let unlock_ts: Timestamp = 1669870800;
+        let both_stars = |part1_sec, part2_sec| {
+            [
+                ((1, 1), unlock_ts + part1_sec),
+                ((1, 2), unlock_ts + part2_sec),
+            ]
+        };
+        let event = event(
+            2022,
+            vec![
+                member(1, None, &both_stars(100, 300)),
+                member(2, None, &both_stars(50, 200)),
+                member(3, None, &both_stars(150, 250)),
+                member(4, None, &both_stars(100, 300)),
+                member(5, None, &[]),
+                member(6, None, &both_stars(0, 5000)),
+            ],
+        );
+        let ranked_ids = |name| {
+            let order = LeaderboardOrder::try_from(name).unwrap();
+            event
+                .build_leaderboard(order, None, &config, None)
+                .get_members()
+                .iter()
+                .map(ScoredMember::get_id)
+                .collect::<Vec<_>>()
+        };
+
+        // Lowest total time first, 1, 3 and 4 tying at 400 seconds
+        assert_eq!(ranked_ids("time_since_unlock"), [2, 3, 1, 4, 5, 6]);
+        // Most stars first, 1 to 4 and 6 tying at 2 stars
+        assert_eq!(ranked_ids("stars"), [2, 3, 1, 4, 6, 5]);
+        // Lowest part 2 delta first, 5 without any ranking last even though
+        // 6 took longer than the unsolved star penalty
+        assert_eq!(ranked_ids("part2_delta"), [3, 2, 1, 4, 6, 5]);
     }
 }
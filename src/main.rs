@@ -2,68 +2,169 @@
 
 mod aoc;
 mod app;
+mod cache;
+mod calendar;
+mod check;
+mod cli;
+mod clock;
+mod day;
+mod error;
 mod events;
 mod leaderboard;
 mod leaders;
 mod overall;
+mod reload;
 mod routes;
+mod scheduler;
+mod scoring;
 mod util;
 
-use app::AppSettings;
+use aoc::{latest_event_year, MemberSource, FIRST_EVENT_YEAR};
+use app::{AppSettings, SettingsHandle};
+use check::run_check;
+use clap::Parser;
+use cli::{Args, Command};
 use env_logger::Builder;
 use leaders::EventManager;
-use log::{error, info, LevelFilter};
+use log::{error, info, warn, LevelFilter};
+use reload::spawn_settings_watcher;
 use rocket::routes;
 use rocket_contrib::templates::Template;
+use scheduler::spawn_refresh_scheduler;
+use std::env;
 use std::process::exit;
-use std::sync::{Arc, RwLock};
-
-const SETTINGS_FILE: &str = "settings";
+use std::sync::Arc;
 
 fn main() {
+    let args = Args::parse();
+
     // TODO: currently Rocket doesn't provide a nice a nice way to write
     // app logs so we take over with env_logger - revisit this once issue
     // https://github.com/SergioBenitez/Rocket/issues/21 is resolved
-    Builder::new()
-        .filter_level(LevelFilter::Info)
-        .parse_default_env()
-        .init();
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info).parse_default_env();
+    if let Some(level) = args.log_level {
+        builder.filter_level(level);
+    }
+    builder.init();
+
+    // Rocket reads its configuration from Rocket.toml, which can be
+    // overridden with environment variables, so that's how the command-line
+    // options are passed on to it
+    if let Some(address) = &args.address {
+        env::set_var("ROCKET_ADDRESS", address);
+    }
+    if let Some(port) = args.port {
+        env::set_var("ROCKET_PORT", port.to_string());
+    }
 
     let settings =
-        AppSettings::load_from_file(SETTINGS_FILE).unwrap_or_else(|err| {
+        AppSettings::load_from_file(&args.config).unwrap_or_else(|err| {
             error!("Failed to load settings: {}", err.to_string());
             exit(1);
         });
 
+    if let Some(Command::Check) = args.command {
+        let source = build_member_source(&settings);
+        exit(if run_check(&settings, &source) { 0 } else { 1 });
+    }
+
+    info!("aoc_base_url = {}", settings.aoc_base_url);
+    if let Some(dir) = &settings.fixtures_dir {
+        info!("fixtures_dir = {}", dir.display());
+    };
     info!("leaderboard_ids = {:?}", settings.leaderboard_ids);
+    let mut years = settings.year_leaderboard_ids.keys().collect::<Vec<_>>();
+    years.sort_unstable();
+    for year in years {
+        info!(
+            "years.{}.leaderboard_ids = {:?}",
+            year, settings.year_leaderboard_ids[year]
+        );
+    }
     info!(
         "leaderboard_default_order = {}",
         serde_json::to_string(&settings.leaderboard_default_order).unwrap()
     );
+    info!(
+        "unsolved_star_penalty_sec = {}",
+        settings.unsolved_star_penalty_sec
+    );
     info!(
         "leaderboard_update_sec = {}",
         settings.leaderboard_update_sec
     );
+    info!("past_event_update_sec = {}", settings.past_event_update_sec);
+    info!("refresh_concurrency = {}", settings.refresh_concurrency);
+    info!("fetch_timeout_sec = {}", settings.fetch_timeout_sec);
+    info!(
+        "min_fetch_interval_sec = {}",
+        settings.min_fetch_interval_sec
+    );
+    info!(
+        "max_requests_per_minute = {}",
+        settings.max_requests_per_minute
+    );
+    info!("fetch_retries = {}", settings.fetch_retries);
+    info!("fetch_retry_delay_ms = {}", settings.fetch_retry_delay_ms);
+    if settings.leaderboard_update_sec < settings.min_fetch_interval_sec {
+        warn!(
+            "leaderboard_update_sec is lower than min_fetch_interval_sec, \
+            leaderboards will only be fetched every {} seconds",
+            settings.min_fetch_interval_sec
+        );
+    }
     info!("exclude_members = {:?}", settings.exclude_members);
     if let Some(year) = settings.latest_event_year {
         info!("latest_event_year = {}", year);
     };
+    if let Some(dir) = &settings.cache_dir {
+        info!("cache_dir = {}", dir.display());
+    };
+    if let Some(now) = settings.simulated_now {
+        info!("simulated_now = {}", now.to_rfc3339());
+        info!("freeze_simulated_now = {}", settings.freeze_simulated_now);
+    };
 
+    let source = build_member_source(&settings);
     let event_mgr = EventManager::new(
-        settings.leaderboard_ids.clone(),
-        settings.session_cookie.clone(),
-        settings.leaderboard_update_sec,
-        settings.exclude_members.iter().cloned().collect(),
+        settings.event_config(source),
+        settings.cache_dir.clone(),
     );
+    let latest_year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock))
+        .max(latest_event_year(&settings.clock));
+    event_mgr.load_cache(FIRST_EVENT_YEAR..=latest_year);
+
+    // Refresh the latest event in the background from the start
+    let default_year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
+    event_mgr.refresh_trigger().request(default_year);
+    let event_mgr = Arc::new(event_mgr);
+    spawn_refresh_scheduler(event_mgr.clone(), settings.refresh_concurrency)
+        .unwrap_or_else(|err| {
+            error!("Failed to start refresh scheduler: {}", err);
+            exit(1);
+        });
+
+    let settings = Arc::new(SettingsHandle::new(settings));
+    spawn_settings_watcher(args.config, settings.clone(), event_mgr.clone())
+        .unwrap_or_else(|err| {
+            error!("Failed to watch settings file: {}", err);
+            exit(1);
+        });
 
     rocket::ignite()
-        .manage(Arc::new(settings))
-        .manage(Arc::new(RwLock::new(event_mgr)))
+        .manage(settings)
+        .manage(event_mgr)
         .mount(
             "/",
             routes![
                 routes::leaderboard,
                 routes::leaderboard_year,
+                routes::day_timings,
                 routes::events,
                 routes::events_year,
                 routes::overall,
@@ -72,3 +173,10 @@ fn main() {
         .attach(Template::fairing())
         .launch();
 }
+
+fn build_member_source(settings: &AppSettings) -> MemberSource {
+    settings.member_source(None).unwrap_or_else(|err| {
+        error!("Failed to initialize fetcher: {}", err);
+        exit(1);
+    })
+}
@@ -1,54 +1,71 @@
 use crate::aoc::*;
+use crate::calendar::EventCalendar;
+use crate::error::*;
 use crate::leaders::*;
+use crate::scoring::*;
 use crate::util::*;
 use crate::AppSettings;
 use chrono::{DateTime, Utc};
-use log::error;
-use rocket::http::Status;
 use rocket_contrib::templates::Template;
 use serde::Serialize;
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::error::Error;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 const MIN_COL_WIDTH: usize = 6;
 
 pub fn render_overall(
     settings: &AppSettings,
-    event_mgr: Arc<RwLock<EventManager>>,
+    event_mgr: Arc<EventManager>,
     leaderboard_order: Option<LeaderboardOrder>,
-) -> Result<Template, Status> {
+) -> Result<Template, ErrorPage> {
     let from_year = FIRST_EVENT_YEAR;
-    let to_year = settings.latest_event_year.unwrap_or_else(latest_event_year);
+    let to_year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
     let order = leaderboard_order.unwrap_or(settings.leaderboard_default_order);
     let leaderboard =
-        build_overall_leaderboard(event_mgr, order, from_year, to_year)
-            .map_err(|err| {
-                error!("Failed to fetch events: {}", err);
-                // TODO: customize 500 page
-                Status::InternalServerError
-            })?;
+        match build_overall_leaderboard(event_mgr, order, from_year, to_year) {
+            Ok(Some(leaderboard)) => leaderboard,
+            Ok(None) => return Ok(render_refreshing(settings, to_year)),
+            Err(err) => {
+                return Err(render_fetch_error(settings, to_year, &err))
+            }
+        };
     let context = Context::build(settings, to_year, leaderboard, order);
     Ok(Template::render("overall", context))
 }
 
 fn build_overall_leaderboard(
-    event_mgr: Arc<RwLock<EventManager>>,
+    event_mgr: Arc<EventManager>,
     order: LeaderboardOrder,
     from_year: EventYear,
     to_year: EventYear,
-) -> Result<OverallLeaderboard, Box<dyn Error>> {
+) -> Result<Option<OverallLeaderboard>, Arc<FetchError>> {
     let years = (from_year..=to_year).into_iter().collect::<Vec<_>>();
     let mut updated_at = SystemTime::now();
     let mut member_map: HashMap<MemberId, OverallScoredMember> = HashMap::new();
+    let mut refreshing = false;
+    let mut stale_years = Vec::new();
 
-    // TODO: fetch leaderboards concurrently
+    // Request all years up front so that missing events are fetched
+    // concurrently by the refresh scheduler
+    event_mgr.request_refresh(&years);
     for &year in years.iter() {
         let leaderboard =
-            get_leaderboard(event_mgr.clone(), year, order, None)?;
+            match get_leaderboard(event_mgr.clone(), year, order, None) {
+                LeaderboardStatus::Ready(leaderboard) => leaderboard,
+                LeaderboardStatus::Refreshing => {
+                    refreshing = true;
+                    continue;
+                }
+                LeaderboardStatus::Failed(err) => return Err(err),
+            };
         updated_at = updated_at.min(leaderboard.updated_at());
+        if leaderboard.refresh_error().is_some() {
+            stale_years.push(year);
+        }
         for member in leaderboard.get_members() {
             member_map
                 .entry(member.get_id())
@@ -57,81 +74,116 @@ fn build_overall_leaderboard(
         }
     }
 
+    if refreshing {
+        return Ok(None);
+    }
+
+    // Members missing from an event score as if they had no stars in it
+    let config = event_mgr.config();
+    let strategy = order.strategy();
+    for &year in years.iter() {
+        let empty_score =
+            strategy.empty_score(&EventCalendar::new(year), None, &config);
+        for member in member_map
+            .values_mut()
+            .filter(|member| !member.scores.contains_key(&year))
+        {
+            member.add_score(year, empty_score);
+        }
+    }
+
     let mut members = member_map.into_values().collect::<Vec<_>>();
-    members.sort_unstable();
-    members.reverse();
+    let lower_is_better = strategy.lower_is_better();
+    members.sort_unstable_by(|a, b| a.rank_cmp(b, lower_is_better));
 
-    Ok(OverallLeaderboard {
+    Ok(Some(OverallLeaderboard {
         updated_at,
         years,
+        stale_years,
         members,
-    })
+    }))
 }
 
 #[derive(Serialize)]
 struct OverallLeaderboard {
     updated_at: SystemTime,
     years: Vec<EventYear>,
+    stale_years: Vec<EventYear>,
     members: Vec<OverallScoredMember>,
 }
 
-#[derive(Eq, Serialize)]
+#[derive(Serialize)]
 struct OverallScoredMember {
     id: MemberId,
     name: String,
     scores: HashMap<EventYear, Score>,
     last_star: Timestamp,
     overall_score: Score,
+    unscored_years: usize,
 }
 
 impl OverallScoredMember {
     fn from(member: &ScoredMember, year: EventYear) -> Self {
-        Self {
+        let mut overall = Self {
             id: member.get_id(),
             name: member.get_name(),
-            scores: vec![(year, member.get_score())].into_iter().collect(),
+            scores: HashMap::new(),
             last_star: member.get_last_star(),
-            overall_score: member.get_score(),
-        }
+            overall_score: 0,
+            unscored_years: 0,
+        };
+        overall.add_score(year, member.get_score());
+        overall
     }
 
+    // Years without a score are left out of the overall score, which is only
+    // missing if no year has one
     fn add_score(&mut self, year: EventYear, score: Score) {
         self.scores.insert(year, score);
-        self.overall_score = self.scores.values().sum();
-    }
-}
-
-impl Ord for OverallScoredMember {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.overall_score
-            .cmp(&other.overall_score)
-            .then(other.last_star.cmp(&self.last_star))
-            .then(other.id.cmp(&self.id))
-    }
-}
-
-impl PartialOrd for OverallScoredMember {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+        let scores = self
+            .scores
+            .values()
+            .copied()
+            .filter(|&score| score != NO_SCORE)
+            .collect::<Vec<_>>();
+        self.unscored_years = self.scores.len() - scores.len();
+        self.overall_score = if scores.is_empty() {
+            NO_SCORE
+        } else {
+            scores.iter().sum()
+        };
     }
-}
 
-impl PartialEq for OverallScoredMember {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+    // Members with fewer years without a score first, then best overall
+    // score first, ties going to whoever got their last star first
+    fn rank_cmp(&self, other: &Self, lower_is_better: bool) -> Ordering {
+        let by_score = if lower_is_better {
+            self.overall_score.cmp(&other.overall_score)
+        } else {
+            other.overall_score.cmp(&self.overall_score)
+        };
+        self.unscored_years
+            .cmp(&other.unscored_years)
+            .then(by_score)
+            .then(self.last_star.cmp(&other.last_star))
+            .then(self.id.cmp(&other.id))
     }
 }
 
 #[derive(Serialize)]
 struct Context<'a> {
+    aoc_base_url: &'a str,
     year: EventYear,
     leaderboard_name: &'a str,
     updated_at: String,
     leaderboard_update_sec: u64,
     leaderboard_order: LeaderboardOrder,
     leaderboard_default_order: &'a LeaderboardOrder,
+    orderings: Vec<StrategyInfo>,
+    score_suffix: &'static str,
     table_head_pad: String,
     years: Vec<EventYear>,
+    stale_years: Vec<EventYear>,
     col_header: Vec<String>,
     rank: Vec<String>,
     overall_score: Vec<String>,
@@ -152,19 +204,29 @@ impl<'a> Context<'a> {
             .to_string();
         let leaderboard_update_sec = settings.leaderboard_update_sec;
         let years = leaderboard.years.clone();
+        let stale_years = leaderboard.stale_years.clone();
 
-        let col_width = years
+        let strategy = leaderboard_order.strategy();
+        let year_scores = leaderboard
+            .members
             .iter()
-            .map(|year| {
-                leaderboard
-                    .members
+            .map(|member| {
+                years
                     .iter()
-                    .map(|member| member.scores.get(year).unwrap_or(&0))
-                    .max()
-                    .unwrap_or(&0)
+                    .map(|year| {
+                        strategy.format_score(
+                            *member.scores.get(year).unwrap_or(&0),
+                        )
+                    })
+                    .collect::<Vec<_>>()
             })
+            .collect::<Vec<_>>();
+        let col_width = year_scores
+            .iter()
+            .flatten()
+            .map(String::len)
             .max()
-            .map(|max_score| (2 + number_width(*max_score)).max(MIN_COL_WIDTH))
+            .map(|max_len| (2 + max_len).max(MIN_COL_WIDTH))
             .unwrap_or(MIN_COL_WIDTH);
         let col_header = years
             .iter()
@@ -179,33 +241,30 @@ impl<'a> Context<'a> {
             .map(|rank| format!("{:width$}) ", rank, width = rank_width))
             .collect::<Vec<_>>();
 
-        let score_width = number_width(
-            leaderboard
-                .members
-                .get(0)
-                .map(|member| member.overall_score)
-                .unwrap_or(0),
-        );
-        let overall_score = leaderboard
+        let overall_scores = leaderboard
             .members
             .iter()
-            .map(|member| {
-                format!("{:width$}", member.overall_score, width = score_width,)
-            })
+            .map(|member| strategy.format_score(member.overall_score))
+            .collect::<Vec<_>>();
+        let score_width =
+            overall_scores.iter().map(String::len).max().unwrap_or(1);
+        let overall_score = overall_scores
+            .iter()
+            .map(|score| format!("{:>width$}", score, width = score_width))
             .collect::<Vec<_>>();
 
         let table_head_pad = vec![' '; rank_width + score_width + 2]
             .into_iter()
             .collect();
 
-        let scores = leaderboard
-            .members
+        let scores = year_scores
             .iter()
-            .map(|member| {
-                years
+            .map(|member_scores| {
+                member_scores
                     .iter()
-                    .map(|year| member.scores.get(year).unwrap_or(&0))
-                    .map(|score| format!("{:width$}", score, width = col_width))
+                    .map(|score| {
+                        format!("{:>width$}", score, width = col_width)
+                    })
                     .collect::<Vec<_>>()
                     .concat()
             })
@@ -218,14 +277,18 @@ impl<'a> Context<'a> {
             .collect();
 
         Self {
+            aoc_base_url: &settings.aoc_base_url,
             year,
             leaderboard_name,
             updated_at,
             leaderboard_update_sec,
             leaderboard_order,
             leaderboard_default_order: &settings.leaderboard_default_order,
+            orderings: strategy_infos(),
+            score_suffix: strategy.score_suffix(),
             table_head_pad,
             years,
+            stale_years,
             col_header,
             rank,
             overall_score,
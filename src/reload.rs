@@ -0,0 +1,136 @@
+use crate::app::*;
+use crate::leaders::*;
+use log::{error, info, warn};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const SETTINGS_EXTENSIONS: [&str; 6] =
+    ["toml", "json", "yaml", "yml", "hjson", "ini"];
+const RELOAD_DELAY: Duration = Duration::from_secs(2);
+
+// Watch the settings file and reload the settings whenever it changes. The
+// directory is watched rather than the file itself, since editors often
+// replace files instead of writing to them.
+pub fn spawn_settings_watcher(
+    settings_file: String,
+    settings: Arc<SettingsHandle>,
+    event_mgr: Arc<EventManager>,
+) -> Result<JoinHandle<()>, Box<dyn Error>> {
+    let path = find_settings_file(&settings_file)
+        .ok_or("settings file not found")?
+        .canonicalize()?;
+    let dir = path.parent().ok_or("settings file has no directory")?;
+
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, RELOAD_DELAY)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    info!("Watching {} for changes", path.display());
+
+    let handle = thread::Builder::new().name("settings".to_string()).spawn(
+        move || {
+            // Events stop once the watcher is dropped
+            let _watcher = watcher;
+            for event in rx {
+                match event {
+                    DebouncedEvent::Create(changed)
+                    | DebouncedEvent::Write(changed)
+                    | DebouncedEvent::Rename(_, changed)
+                        if changed == path =>
+                    {
+                        reload_settings(&settings_file, &settings, &event_mgr)
+                    }
+                    DebouncedEvent::Error(err, _) => {
+                        warn!("Error watching settings file: {}", err)
+                    }
+                    _ => {}
+                }
+            }
+        },
+    )?;
+    Ok(handle)
+}
+
+// Same lookup as the config crate: the file name as given, or with one of the
+// supported extensions
+fn find_settings_file(settings_file: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(settings_file);
+    if path.is_file() {
+        return Some(path);
+    }
+    SETTINGS_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+fn reload_settings(
+    settings_file: &str,
+    settings: &SettingsHandle,
+    event_mgr: &EventManager,
+) {
+    info!("Reloading settings from {}", settings_file);
+    let mut new_settings = match AppSettings::load_from_file(settings_file) {
+        Ok(new_settings) => new_settings,
+        Err(err) => {
+            error!("Failed to reload settings, keeping current ones: {}", err);
+            return;
+        }
+    };
+    let source = match new_settings
+        .member_source(event_mgr.config().source.fetcher())
+    {
+        Ok(source) => source,
+        Err(err) => {
+            error!("Failed to reload settings, keeping current ones: {}", err);
+            return;
+        }
+    };
+
+    let current = settings.current();
+    warn_restart_needed(&current, &new_settings);
+    // Keep simulated time running rather than restarting it
+    if new_settings.simulated_now == current.simulated_now
+        && new_settings.freeze_simulated_now == current.freeze_simulated_now
+    {
+        new_settings.clock = current.clock;
+    }
+    event_mgr.reconfigure(new_settings.event_config(source));
+    settings.replace(new_settings);
+    info!("Settings reloaded");
+}
+
+// Settings that are only read on startup
+fn warn_restart_needed(old: &AppSettings, new: &AppSettings) {
+    let changed = [
+        (
+            "refresh_concurrency",
+            old.refresh_concurrency != new.refresh_concurrency,
+        ),
+        (
+            "fetch_timeout_sec",
+            old.fetch_timeout_sec != new.fetch_timeout_sec,
+        ),
+        (
+            "min_fetch_interval_sec",
+            old.min_fetch_interval_sec != new.min_fetch_interval_sec,
+        ),
+        (
+            "max_requests_per_minute",
+            old.max_requests_per_minute != new.max_requests_per_minute,
+        ),
+        ("fetch_retries", old.fetch_retries != new.fetch_retries),
+        (
+            "fetch_retry_delay_ms",
+            old.fetch_retry_delay_ms != new.fetch_retry_delay_ms,
+        ),
+        ("cache_dir", old.cache_dir != new.cache_dir),
+    ];
+    for (setting, _) in changed.iter().filter(|(_, changed)| *changed) {
+        warn!("{} changed, restart the app for it to take effect", setting);
+    }
+}
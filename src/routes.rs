@@ -1,4 +1,7 @@
 use crate::aoc::*;
+use crate::app::SettingsHandle;
+use crate::day::*;
+use crate::error::*;
 use crate::events::*;
 use crate::leaderboard::*;
 use crate::leaders::*;
@@ -6,59 +9,100 @@ use crate::overall::*;
 use crate::AppSettings;
 use rocket::{get, http::Status, State};
 use rocket_contrib::templates::Template;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 #[get("/?<as_of>&<order>")]
 pub fn leaderboard(
-    settings: State<Arc<AppSettings>>,
-    event_mgr: State<Arc<RwLock<EventManager>>>,
+    settings: State<Arc<SettingsHandle>>,
+    event_mgr: State<Arc<EventManager>>,
     as_of: Option<AsOf>,
     order: Option<LeaderboardOrder>,
-) -> Result<Template, Status> {
-    let year = settings.latest_event_year.unwrap_or_else(latest_event_year);
+) -> Result<Template, ErrorPage> {
+    let settings = settings.current();
+    let year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
     render_leaderboard(&settings, event_mgr.clone(), year, order, as_of)
 }
 
 #[get("/<year>?<as_of>&<order>")]
 pub fn leaderboard_year(
-    settings: State<Arc<AppSettings>>,
-    event_mgr: State<Arc<RwLock<EventManager>>>,
+    settings: State<Arc<SettingsHandle>>,
+    event_mgr: State<Arc<EventManager>>,
     year: EventYear,
     as_of: Option<AsOf>,
     order: Option<LeaderboardOrder>,
-) -> Result<Template, Status> {
-    if Some(year) == settings.latest_event_year || is_valid_event_year(year) {
+) -> Result<Template, ErrorPage> {
+    let settings = settings.current();
+    if Some(year) == settings.latest_event_year
+        || is_valid_event_year(year, &settings.clock)
+    {
         render_leaderboard(&settings, event_mgr.clone(), year, order, as_of)
     } else {
-        // TODO: customize 404 page
-        Err(Status::NotFound)
+        Err(render_not_found(&settings, year))
     }
 }
 
+#[get("/<year>/day/<day>?<as_of>")]
+pub fn day_timings(
+    settings: State<Arc<SettingsHandle>>,
+    event_mgr: State<Arc<EventManager>>,
+    year: EventYear,
+    day: PuzzleDay,
+    as_of: Option<AsOf>,
+) -> Result<Template, ErrorPage> {
+    let settings = settings.current();
+    if Some(year) == settings.latest_event_year
+        || is_valid_event_year(year, &settings.clock)
+    {
+        render_day(&settings, event_mgr.clone(), year, day, as_of)
+    } else {
+        Err(render_not_found(&settings, year))
+    }
+}
+
+fn render_not_found(settings: &AppSettings, year: EventYear) -> ErrorPage {
+    let latest_year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
+    render_error(
+        settings,
+        latest_year,
+        Status::NotFound,
+        format!("There is no Advent of Code event for {}.", year),
+    )
+}
+
 #[get("/events")]
-pub fn events(settings: State<Arc<AppSettings>>) -> Template {
-    let year = settings.latest_event_year.unwrap_or_else(latest_event_year);
+pub fn events(settings: State<Arc<SettingsHandle>>) -> Template {
+    let settings = settings.current();
+    let year = settings
+        .latest_event_year
+        .unwrap_or_else(|| latest_event_year(&settings.clock));
     render_events(&settings, year)
 }
 
 #[get("/<year>/events")]
 pub fn events_year(
-    settings: State<Arc<AppSettings>>,
+    settings: State<Arc<SettingsHandle>>,
     year: EventYear,
-) -> Result<Template, Status> {
-    if Some(year) == settings.latest_event_year || is_valid_event_year(year) {
+) -> Result<Template, ErrorPage> {
+    let settings = settings.current();
+    if Some(year) == settings.latest_event_year
+        || is_valid_event_year(year, &settings.clock)
+    {
         Ok(render_events(&settings, year))
     } else {
-        // TODO: customize 404 page
-        Err(Status::NotFound)
+        Err(render_not_found(&settings, year))
     }
 }
 
 #[get("/overall?<order>")]
 pub fn overall(
-    settings: State<Arc<AppSettings>>,
-    event_mgr: State<Arc<RwLock<EventManager>>>,
+    settings: State<Arc<SettingsHandle>>,
+    event_mgr: State<Arc<EventManager>>,
     order: Option<LeaderboardOrder>,
-) -> Result<Template, Status> {
+) -> Result<Template, ErrorPage> {
+    let settings = settings.current();
     render_overall(&settings, event_mgr.clone(), order)
 }
@@ -0,0 +1,108 @@
+use crate::aoc::*;
+use crate::leaders::*;
+use log::debug;
+use std::collections::HashSet;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Years whose leaderboards have been requested and must be kept up to date,
+// along with a flag to wake up the scheduler when a new year is requested
+#[derive(Default)]
+pub struct RefreshTrigger {
+    state: Mutex<(HashSet<EventYear>, bool)>,
+    wake: Condvar,
+}
+
+impl RefreshTrigger {
+    pub fn request(&self, year: EventYear) {
+        self.request_all([year]);
+    }
+
+    // Request several years at once, so that the scheduler wakes up only
+    // once and refreshes all of them together
+    pub fn request_all<I>(&self, years: I)
+    where
+        I: IntoIterator<Item = EventYear>,
+    {
+        // TODO: handle LockResult errors
+        let mut state = self.state.lock().unwrap();
+        for year in years {
+            if state.0.insert(year) {
+                debug!("{} event is now refreshed in the background", year);
+                state.1 = true;
+            }
+        }
+        if state.1 {
+            self.wake.notify_one();
+        }
+    }
+
+    // Wake up the scheduler to check which events are due, e.g. after some
+    // were invalidated
+    pub fn wake(&self) {
+        // TODO: handle LockResult errors
+        self.state.lock().unwrap().1 = true;
+        self.wake.notify_one();
+    }
+
+    pub fn active_years(&self) -> Vec<EventYear> {
+        // TODO: handle LockResult errors
+        self.state.lock().unwrap().0.iter().copied().collect()
+    }
+
+    fn wait(&self, timeout: Duration) {
+        // TODO: handle LockResult errors
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .wake
+            .wait_timeout_while(state, timeout, |(_, woken)| !*woken)
+            .unwrap();
+        state.1 = false;
+    }
+}
+
+pub fn spawn_refresh_scheduler(
+    event_mgr: Arc<EventManager>,
+    concurrency: usize,
+) -> io::Result<JoinHandle<()>> {
+    let trigger = event_mgr.refresh_trigger();
+    thread::Builder::new()
+        .name("refresh".to_string())
+        .spawn(move || loop {
+            refresh_events(&event_mgr, event_mgr.due_years(), concurrency);
+
+            let wait = event_mgr.next_refresh_in();
+            debug!("Next refresh in {} seconds", wait.as_secs());
+            trigger.wait(wait);
+        })
+}
+
+// Refresh events concurrently, with at most `concurrency` of them being
+// fetched at the same time, and return once all of them are done
+fn refresh_events(
+    event_mgr: &EventManager,
+    years: Vec<EventYear>,
+    concurrency: usize,
+) {
+    if years.is_empty() {
+        return;
+    }
+
+    debug!("Refreshing {} events: {:?}", years.len(), years);
+    let workers = concurrency.clamp(1, years.len());
+    let queue = Mutex::new(years);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                // TODO: handle LockResult errors
+                let next_year = queue.lock().unwrap().pop();
+                match next_year {
+                    Some(year) => event_mgr.refresh_event(year),
+                    None => break,
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,472 @@
+use crate::aoc::*;
+use crate::calendar::EventCalendar;
+use crate::leaders::{Event, EventConfig, ScoredMember};
+use crate::util::format_duration;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Available leaderboard orderings, in the order they are listed on the pages;
+// adding one only requires implementing ScoringStrategy and listing it here
+pub static SCORING_STRATEGIES: &[&dyn ScoringStrategy] =
+    &[&LocalScore, &Stars, &TimeSinceUnlock, &Part2Delta];
+
+const PUZZLE_PARTS: [PuzzlePart; 2] = [1, 2];
+
+// Score of members that a strategy has nothing to rank by, e.g. those without
+// both stars of any day when ordering by part 2 delta, who always rank last
+pub const NO_SCORE: Score = Score::MAX;
+
+// Settings shared by all strategies
+pub struct ScoringConfig {
+    pub unsolved_star_penalty_sec: u64,
+}
+
+// Scores the members of an event, the highest score ranking first unless
+// lower is better
+pub trait ScoringStrategy: Sync {
+    // Name used in URLs and settings
+    fn name(&self) -> &'static str;
+
+    // Name shown on the pages
+    fn title(&self) -> &'static str;
+
+    // HTML description shown on the pages, completing "[Title], which..."
+    fn description(&self) -> &'static str;
+
+    // Appended to scores on the pages, e.g. to tell star counts apart
+    fn score_suffix(&self) -> &'static str {
+        ""
+    }
+
+    fn lower_is_better(&self) -> bool {
+        false
+    }
+
+    fn format_score(&self, score: Score) -> String {
+        score.to_string()
+    }
+
+    // Score of members without any stars, e.g. those missing from an event
+    fn empty_score(
+        &self,
+        _calendar: &EventCalendar,
+        _as_of: Option<Timestamp>,
+        _config: &EventConfig,
+    ) -> Score {
+        0
+    }
+
+    fn score(
+        &self,
+        event: &Event,
+        as_of: Option<Timestamp>,
+        config: &EventConfig,
+    ) -> Vec<ScoredMember>;
+}
+
+pub fn find_strategy(name: &str) -> Option<&'static dyn ScoringStrategy> {
+    SCORING_STRATEGIES
+        .iter()
+        .copied()
+        .find(|strategy| strategy.name() == name)
+}
+
+#[derive(Serialize)]
+pub struct StrategyInfo {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+}
+
+pub fn strategy_infos() -> Vec<StrategyInfo> {
+    SCORING_STRATEGIES
+        .iter()
+        .map(|strategy| StrategyInfo {
+            name: strategy.name(),
+            title: strategy.title(),
+            description: strategy.description(),
+        })
+        .collect()
+}
+
+struct LocalScore;
+
+impl ScoringStrategy for LocalScore {
+    fn name(&self) -> &'static str {
+        "local_score"
+    }
+
+    fn title(&self) -> &'static str {
+        "Local Score"
+    }
+
+    fn description(&self) -> &'static str {
+        "which considers the time each star was acquired. For <code>N</code> \
+        users, the first user to get each star gets <code>N</code> points, the \
+        second gets <code>N-1</code>, and the last gets <code>1</code>."
+    }
+
+    fn score(
+        &self,
+        event: &Event,
+        as_of: Option<Timestamp>,
+        _config: &EventConfig,
+    ) -> Vec<ScoredMember> {
+        let mut puzzles = HashMap::new();
+        for member in event.members().iter() {
+            for (puzzle_id, ts) in member.iter_completed() {
+                if as_of.map(|timestamp| *ts <= timestamp).unwrap_or(true) {
+                    puzzles
+                        .entry(*puzzle_id)
+                        .or_insert_with(BinaryHeap::new)
+                        .push(Reverse((ts, member)));
+                }
+            }
+        }
+
+        let mut scores = HashMap::new();
+        let max_points = event.members().len();
+        for (_, mut solutions) in puzzles.drain() {
+            let mut puzzle_points = max_points;
+            while let Some(Reverse((_, member))) = solutions.pop() {
+                *scores.entry(member).or_insert(0) += puzzle_points;
+                puzzle_points -= 1;
+            }
+        }
+
+        event
+            .members()
+            .iter()
+            .map(|member| {
+                ScoredMember::build(
+                    member,
+                    event.calendar(),
+                    as_of,
+                    *scores.get(member).unwrap_or(&0),
+                )
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+struct Stars;
+
+impl ScoringStrategy for Stars {
+    fn name(&self) -> &'static str {
+        "stars"
+    }
+
+    fn title(&self) -> &'static str {
+        "Stars"
+    }
+
+    fn description(&self) -> &'static str {
+        "which uses the number of stars the user has."
+    }
+
+    fn score_suffix(&self) -> &'static str {
+        "*"
+    }
+
+    fn score(
+        &self,
+        event: &Event,
+        as_of: Option<Timestamp>,
+        _config: &EventConfig,
+    ) -> Vec<ScoredMember> {
+        event
+            .members()
+            .iter()
+            .map(|member| {
+                ScoredMember::build(
+                    member,
+                    event.calendar(),
+                    as_of,
+                    member.star_count(as_of),
+                )
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+struct TimeSinceUnlock;
+
+impl ScoringStrategy for TimeSinceUnlock {
+    fn name(&self) -> &'static str {
+        "time_since_unlock"
+    }
+
+    fn title(&self) -> &'static str {
+        "Time Since Unlock"
+    }
+
+    fn description(&self) -> &'static str {
+        "which adds up the time it took the user to get each star since its \
+        puzzle unlocked, shown in hours, minutes and seconds. Each star of an \
+        unlocked puzzle that the user hasn't got yet adds a fixed penalty. The \
+        lowest total wins."
+    }
+
+    fn lower_is_better(&self) -> bool {
+        true
+    }
+
+    fn format_score(&self, score: Score) -> String {
+        format_duration(score)
+    }
+
+    fn empty_score(
+        &self,
+        calendar: &EventCalendar,
+        as_of: Option<Timestamp>,
+        config: &EventConfig,
+    ) -> Score {
+        unlocked_days(calendar, as_of, config).len()
+            * PUZZLE_PARTS.len()
+            * star_penalty(config)
+    }
+
+    fn score(
+        &self,
+        event: &Event,
+        as_of: Option<Timestamp>,
+        config: &EventConfig,
+    ) -> Vec<ScoredMember> {
+        let calendar = event.calendar();
+        let unlocked_days = unlocked_days(calendar, as_of, config);
+        let penalty = star_penalty(config);
+
+        event
+            .members()
+            .iter()
+            .map(|member| {
+                let total = unlocked_days
+                    .iter()
+                    .flat_map(|&(day, unlock_ts)| {
+                        PUZZLE_PARTS.iter().map(move |&part| {
+                            member
+                                .get_star_ts((day, part), as_of)
+                                .and_then(|ts| {
+                                    usize::try_from(ts - unlock_ts).ok()
+                                })
+                                .unwrap_or(penalty)
+                        })
+                    })
+                    .sum();
+                ScoredMember::build(member, calendar, as_of, total)
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+struct Part2Delta;
+
+impl ScoringStrategy for Part2Delta {
+    fn name(&self) -> &'static str {
+        "part2_delta"
+    }
+
+    fn title(&self) -> &'static str {
+        "Part 2 Delta"
+    }
+
+    fn description(&self) -> &'static str {
+        "which uses the median time it took the user to get from the first \
+        to the second star of each day they got both stars, shown in hours, \
+        minutes and seconds. Users who haven't got both stars of any day yet \
+        rank last. The lowest median wins."
+    }
+
+    fn lower_is_better(&self) -> bool {
+        true
+    }
+
+    fn format_score(&self, score: Score) -> String {
+        if score == NO_SCORE {
+            "-".to_string()
+        } else {
+            format_duration(score)
+        }
+    }
+
+    fn empty_score(
+        &self,
+        _calendar: &EventCalendar,
+        _as_of: Option<Timestamp>,
+        _config: &EventConfig,
+    ) -> Score {
+        NO_SCORE
+    }
+
+    fn score(
+        &self,
+        event: &Event,
+        as_of: Option<Timestamp>,
+        _config: &EventConfig,
+    ) -> Vec<ScoredMember> {
+        let calendar = event.calendar();
+        event
+            .members()
+            .iter()
+            .map(|member| {
+                let mut deltas = calendar
+                    .days()
+                    .filter_map(|day| member.get_part2_delta(day, as_of))
+                    .filter_map(|delta| usize::try_from(delta).ok())
+                    .collect::<Vec<_>>();
+                deltas.sort_unstable();
+                let median = match deltas.len() {
+                    0 => NO_SCORE,
+                    len if len % 2 == 0 => {
+                        (deltas[len / 2 - 1] + deltas[len / 2]) / 2
+                    }
+                    len => deltas[len / 2],
+                };
+                ScoredMember::build(member, calendar, as_of, median)
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+// Days unlocked as of the given time, or by now, along with their unlock
+// times
+fn unlocked_days(
+    calendar: &EventCalendar,
+    as_of: Option<Timestamp>,
+    config: &EventConfig,
+) -> Vec<(PuzzleDay, Timestamp)> {
+    let now = as_of.unwrap_or_else(|| config.clock.now().timestamp());
+    calendar
+        .days()
+        .filter_map(|day| {
+            calendar
+                .unlock_timestamp(day)
+                .filter(|&unlock_ts| unlock_ts <= now)
+                .map(|unlock_ts| (day, unlock_ts))
+        })
+        .collect()
+}
+
+fn star_penalty(config: &EventConfig) -> Score {
+    usize::try_from(config.scoring.unsolved_star_penalty_sec).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aoc::tests::member;
+    use crate::leaders::tests::{config_with_clock, event, frozen_at};
+
+    // Unlock times of the first two days of 2022
+    const DAY1_TS: Timestamp = 1669870800;
+    const DAY2_TS: Timestamp = DAY1_TS + 86400;
+
+    fn scores_by_id(members: Vec<ScoredMember>) -> HashMap<MemberId, Score> {
+        members
+            .iter()
+            .map(|member| (member.get_id(), member.get_score()))
+            .collect()
+    }
+
+    #[test]
+    fn time_since_unlock_penalizes_unsolved_stars() {
+        let config = config_with_clock(frozen_at("2022-12-02T12:00:00Z"));
+        let event = event(
+            2022,
+            vec![
+                member(
+                    1,
+                    None,
+                    &[
+                        ((1, 1), DAY1_TS + 100),
+                        ((1, 2), DAY1_TS + 300),
+                        ((2, 1), DAY2_TS + 50),
+                    ],
+                ),
+                member(2, None, &[]),
+            ],
+        );
+        let scores = scores_by_id(TimeSinceUnlock.score(&event, None, &config));
+        assert_eq!(scores[&1], 100 + 300 + 50 + 1000);
+        assert_eq!(scores[&2], 4 * 1000);
+        assert_eq!(
+            TimeSinceUnlock.empty_score(event.calendar(), None, &config),
+            4 * 1000
+        );
+    }
+
+    #[test]
+    fn time_since_unlock_only_counts_days_unlocked_as_of() {
+        let config = config_with_clock(frozen_at("2022-12-03T12:00:00Z"));
+        let event = event(
+            2022,
+            vec![member(
+                1,
+                None,
+                &[
+                    ((1, 1), DAY1_TS + 100),
+                    ((1, 2), DAY2_TS + 10),
+                    ((2, 1), DAY2_TS + 20),
+                ],
+            )],
+        );
+        let as_of = Some(DAY2_TS - 1);
+        let scores =
+            scores_by_id(TimeSinceUnlock.score(&event, as_of, &config));
+        assert_eq!(scores[&1], 100 + 1000);
+        assert_eq!(
+            TimeSinceUnlock.empty_score(event.calendar(), as_of, &config),
+            2 * 1000
+        );
+        assert_eq!(
+            TimeSinceUnlock.empty_score(event.calendar(), None, &config),
+            6 * 1000
+        );
+    }
+
+    // Part 2 delta of a member who got both stars of the given days, each
+    // taking the given time from the first to the second star
+    fn part2_delta_score(deltas: &[(PuzzleDay, Timestamp)]) -> Score {
+        let config = config_with_clock(frozen_at("2022-12-05T12:00:00Z"));
+        // The first star of day 5 alone never counts
+        let mut stars = vec![((5, 1), DAY1_TS + 4 * 86400 + 10)];
+        for &(day, delta) in deltas {
+            let part1_ts = DAY1_TS + Timestamp::from(day - 1) * 86400 + 10;
+            stars.push(((day, 1), part1_ts));
+            stars.push(((day, 2), part1_ts + delta));
+        }
+        let event = event(2022, vec![member(1, None, &stars)]);
+        scores_by_id(Part2Delta.score(&event, None, &config))[&1]
+    }
+
+    #[test]
+    fn part2_delta_takes_middle_delta_of_odd_count() {
+        assert_eq!(part2_delta_score(&[(1, 300), (2, 100), (3, 200)]), 200);
+    }
+
+    #[test]
+    fn part2_delta_averages_middle_deltas_of_even_count() {
+        assert_eq!(part2_delta_score(&[(1, 400), (2, 100)]), 250);
+        assert_eq!(
+            part2_delta_score(&[(1, 400), (2, 100), (3, 50), (4, 300)]),
+            200
+        );
+    }
+
+    #[test]
+    fn part2_delta_has_no_score_without_both_stars_of_any_day() {
+        assert_eq!(part2_delta_score(&[]), NO_SCORE);
+        assert_eq!(Part2Delta.format_score(NO_SCORE), "-");
+        let calendar = EventCalendar::new(2022);
+        let config = config_with_clock(frozen_at("2022-12-05T12:00:00Z"));
+        assert_eq!(Part2Delta.empty_score(&calendar, None, &config), NO_SCORE);
+    }
+
+    #[test]
+    fn time_since_unlock_shows_hours_minutes_and_seconds() {
+        assert_eq!(TimeSinceUnlock.format_score(0), "0:00:00");
+        assert_eq!(TimeSinceUnlock.format_score(3661), "1:01:01");
+        assert_eq!(TimeSinceUnlock.format_score(100 * 3600 + 59), "100:00:59");
+    }
+}
@@ -3,3 +3,8 @@ use conv::ConvUtil;
 pub fn number_width(num: usize) -> usize {
     1 + num.value_as::<f64>().unwrap_or(0_f64).log10().floor() as usize
 }
+
+// Hours (however many), minutes and seconds, e.g. "26:03:09"
+pub fn format_duration(secs: usize) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}